@@ -0,0 +1,39 @@
+// Run with `cargo bench --features aesni` to compare the AES-NI backend against software
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use set1::aes::{Aes, Backend, Block, BLOCK_SIZE};
+
+const BLOCKS: usize = 1024;
+const KEY: &[u8; 16] = b"YELLOW SUBMARINE";
+
+fn bench_backend(c: &mut Criterion, name: &str, aes: &Aes) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes((BLOCKS * BLOCK_SIZE) as u64));
+    let mut blocks: Vec<Block> = vec![[0u8; BLOCK_SIZE]; BLOCKS];
+    group.bench_function(format!("{:?} encrypt", aes.backend()), |b| {
+        b.iter(|| {
+            for block in blocks.iter_mut() {
+                aes.encrypt_block(black_box(block));
+            }
+        })
+    });
+    group.bench_function(format!("{:?} decrypt", aes.backend()), |b| {
+        b.iter(|| {
+            for block in blocks.iter_mut() {
+                aes.decrypt_block(black_box(block));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_aes(c: &mut Criterion) {
+    let software = Aes::with_backend(KEY, Backend::Software).unwrap();
+    bench_backend(c, "aes-128 software", &software);
+    let detected = Aes::new(KEY).unwrap();
+    if detected.backend() != Backend::Software {
+        bench_backend(c, "aes-128 hardware", &detected);
+    }
+}
+
+criterion_group!(benches, bench_aes);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use set1::bits;
+
+// Copies of the challenge data make a large input for the keysize search
+const COPIES: usize = 64;
+
+// The original implementation: format every XOR'd byte as binary and count the '1' chars
+fn formatted_hamming_distance(s: &[u8], t: &[u8]) -> usize {
+    s.iter().zip(t.iter()).fold(0, |acc, pair| {
+        acc + format!("{:b}", pair.0 ^ pair.1)
+            .chars()
+            .filter(|&c| c == '1')
+            .count()
+    })
+}
+
+// Average normalized distance between adjacent blocks for keysizes 2 to 40
+fn keysize_search<F: Fn(&[u8], &[u8]) -> usize>(data: &[u8], distance: F) -> f64 {
+    let mut total = 0.0;
+    for keysize in 2..=40 {
+        for pair in data.chunks_exact(keysize * 2) {
+            let (s, t) = pair.split_at(keysize);
+            total += distance(s, t) as f64 / keysize as f64;
+        }
+    }
+    total
+}
+
+fn large_input() -> Vec<u8> {
+    std::fs::read("src/challenge6/6.txt")
+        .unwrap()
+        .repeat(COPIES)
+}
+
+fn bench_keysize_search(c: &mut Criterion) {
+    let data = large_input();
+    let mut group = c.benchmark_group("keysize search");
+    group.sample_size(10);
+    group.bench_function("formatted", |b| {
+        b.iter(|| keysize_search(black_box(&data), formatted_hamming_distance))
+    });
+    group.bench_function("popcount", |b| {
+        b.iter(|| {
+            keysize_search(black_box(&data), |s, t| {
+                bits::hamming_distance(s, t).unwrap() as usize
+            })
+        })
+    });
+    group.finish();
+}
+
+fn bench_hamming_distance(c: &mut Criterion) {
+    let data = large_input();
+    let (s, t) = data.split_at(data.len() / 2);
+    let t = &t[..s.len()];
+    let mut group = c.benchmark_group("hamming distance");
+    group.bench_function("formatted", |b| {
+        b.iter(|| formatted_hamming_distance(black_box(s), black_box(t)))
+    });
+    group.bench_function("popcount", |b| {
+        b.iter(|| bits::hamming_distance(black_box(s), black_box(t)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_keysize_search, bench_hamming_distance);
+criterion_main!(benches);
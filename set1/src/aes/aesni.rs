@@ -0,0 +1,47 @@
+// AES-NI backend: each round is a single instruction, using the round keys of the software key
+// schedule
+
+use super::Block;
+use std::arch::x86_64::{
+    __m128i, _mm_aesdec_si128, _mm_aesdeclast_si128, _mm_aesenc_si128, _mm_aesenclast_si128,
+    _mm_loadu_si128, _mm_storeu_si128, _mm_xor_si128,
+};
+
+pub fn is_supported() -> bool {
+    is_x86_feature_detected!("aes") && is_x86_feature_detected!("sse2")
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn load(block: &Block) -> __m128i {
+    _mm_loadu_si128(block.as_ptr() as *const __m128i)
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn store(state: __m128i, block: &mut Block) {
+    _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, state)
+}
+
+// Callers must make sure the CPU supports AES-NI
+#[target_feature(enable = "aes,sse2")]
+pub unsafe fn encrypt_block(round_keys: &[Block], block: &mut Block) {
+    let last = round_keys.len() - 1;
+    let mut state = _mm_xor_si128(load(block), load(&round_keys[0]));
+    for round_key in &round_keys[1..last] {
+        state = _mm_aesenc_si128(state, load(round_key));
+    }
+    state = _mm_aesenclast_si128(state, load(&round_keys[last]));
+    store(state, block);
+}
+
+// Decrypt with the equivalent inverse cipher, `round_keys` come from
+// equivalent_inverse_round_keys. Callers must make sure the CPU supports AES-NI.
+#[target_feature(enable = "aes,sse2")]
+pub unsafe fn decrypt_block(round_keys: &[Block], block: &mut Block) {
+    let last = round_keys.len() - 1;
+    let mut state = _mm_xor_si128(load(block), load(&round_keys[0]));
+    for round_key in &round_keys[1..last] {
+        state = _mm_aesdec_si128(state, load(round_key));
+    }
+    state = _mm_aesdeclast_si128(state, load(&round_keys[last]));
+    store(state, block);
+}
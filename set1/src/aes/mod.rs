@@ -0,0 +1,598 @@
+/*
+AES block cipher, with 128, 192 or 256-bit keys
+
+Implemented from FIPS-197 so every step can be seen and instrumented: the key schedule, SubBytes,
+ShiftRows, MixColumns and AddRoundKey, together with their inverses. The state is kept as the 16
+input bytes in order, so byte r + 4c is row r of column c.
+*/
+
+#[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+mod aesni;
+
+use std::error::Error;
+use std::fmt;
+
+pub const BLOCK_SIZE: usize = 16;
+
+pub type Block = [u8; BLOCK_SIZE];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySize {
+    Aes128,
+    Aes192,
+    Aes256,
+}
+
+impl KeySize {
+    pub fn from_key_len(len: usize) -> Result<KeySize, InvalidKeyLengthError> {
+        match len {
+            16 => Ok(KeySize::Aes128),
+            24 => Ok(KeySize::Aes192),
+            32 => Ok(KeySize::Aes256),
+            _ => Err(InvalidKeyLengthError { len }),
+        }
+    }
+
+    // Nk in FIPS-197
+    pub fn key_words(self) -> usize {
+        match self {
+            KeySize::Aes128 => 4,
+            KeySize::Aes192 => 6,
+            KeySize::Aes256 => 8,
+        }
+    }
+
+    // Nr in FIPS-197
+    pub fn rounds(self) -> usize {
+        self.key_words() + 6
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidKeyLengthError {
+    pub len: usize,
+}
+
+impl fmt::Display for InvalidKeyLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid AES key length: {} bytes, expected 16, 24 or 32",
+            self.len
+        )
+    }
+}
+
+impl Error for InvalidKeyLengthError {}
+
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+const INV_SBOX: [u8; 256] = [
+    0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+    0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+    0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+    0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+    0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+    0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+    0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+    0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+    0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+    0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+    0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+    0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+    0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+    0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+    0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+    0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
+];
+
+// Round constants of the key schedule, x^(i-1) in GF(2^8)
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+// Step of the cipher whose output is reported to a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    SubBytes,
+    ShiftRows,
+    MixColumns,
+    AddRoundKey,
+}
+
+// Multiply by x in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1
+fn xtime(a: u8) -> u8 {
+    (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+fn sub_word(word: [u8; 4]) -> [u8; 4] {
+    [
+        SBOX[word[0] as usize],
+        SBOX[word[1] as usize],
+        SBOX[word[2] as usize],
+        SBOX[word[3] as usize],
+    ]
+}
+
+fn rot_word(word: [u8; 4]) -> [u8; 4] {
+    [word[1], word[2], word[3], word[0]]
+}
+
+// Key expansion: one round key per round plus the initial one, the key size is picked from the
+// length of the key
+pub fn expand_key(key: &[u8]) -> Result<Vec<Block>, InvalidKeyLengthError> {
+    let key_size = KeySize::from_key_len(key.len())?;
+    let key_words = key_size.key_words();
+    let rounds = key_size.rounds();
+    let mut words: Vec<[u8; 4]> = key
+        .chunks_exact(4)
+        .map(|w| [w[0], w[1], w[2], w[3]])
+        .collect();
+    for i in key_words..4 * (rounds + 1) {
+        let mut temp = words[i - 1];
+        if i % key_words == 0 {
+            temp = sub_word(rot_word(temp));
+            temp[0] ^= RCON[i / key_words - 1];
+        } else if key_words > 6 && i % key_words == 4 {
+            temp = sub_word(temp);
+        }
+        let previous = words[i - key_words];
+        words.push([
+            previous[0] ^ temp[0],
+            previous[1] ^ temp[1],
+            previous[2] ^ temp[2],
+            previous[3] ^ temp[3],
+        ]);
+    }
+    Ok(words
+        .chunks_exact(4)
+        .map(|round_words| {
+            let mut round_key = [0u8; BLOCK_SIZE];
+            for (column, word) in round_words.iter().enumerate() {
+                round_key[4 * column..4 * column + 4].copy_from_slice(word);
+            }
+            round_key
+        })
+        .collect())
+}
+
+pub fn sub_bytes(state: &mut Block) {
+    for byte in state.iter_mut() {
+        *byte = SBOX[*byte as usize];
+    }
+}
+
+pub fn inv_sub_bytes(state: &mut Block) {
+    for byte in state.iter_mut() {
+        *byte = INV_SBOX[*byte as usize];
+    }
+}
+
+// Row r is rotated left by r columns
+pub fn shift_rows(state: &mut Block) {
+    let original = *state;
+    for row in 1..4 {
+        for column in 0..4 {
+            state[row + 4 * column] = original[row + 4 * ((column + row) % 4)];
+        }
+    }
+}
+
+pub fn inv_shift_rows(state: &mut Block) {
+    let original = *state;
+    for row in 1..4 {
+        for column in 0..4 {
+            state[row + 4 * ((column + row) % 4)] = original[row + 4 * column];
+        }
+    }
+}
+
+// Every column is multiplied by a(x) = 3x^3 + x^2 + x + 2 modulo x^4 + 1
+pub fn mix_columns(state: &mut Block) {
+    for column in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        column[0] = gmul(a0, 2) ^ gmul(a1, 3) ^ a2 ^ a3;
+        column[1] = a0 ^ gmul(a1, 2) ^ gmul(a2, 3) ^ a3;
+        column[2] = a0 ^ a1 ^ gmul(a2, 2) ^ gmul(a3, 3);
+        column[3] = gmul(a0, 3) ^ a1 ^ a2 ^ gmul(a3, 2);
+    }
+}
+
+// Every column is multiplied by a^-1(x) = 11x^3 + 13x^2 + 9x + 14 modulo x^4 + 1
+pub fn inv_mix_columns(state: &mut Block) {
+    for column in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        column[0] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+        column[1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+        column[2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+        column[3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+    }
+}
+
+pub fn add_round_key(state: &mut Block, round_key: &Block) {
+    for (byte, key_byte) in state.iter_mut().zip(round_key.iter()) {
+        *byte ^= key_byte;
+    }
+}
+
+// Round keys of the equivalent inverse cipher (FIPS-197 5.3.5): the encryption round keys in
+// reverse order, with InvMixColumns applied to all but the first and the last
+pub fn equivalent_inverse_round_keys(round_keys: &[Block]) -> Vec<Block> {
+    let last = round_keys.len() - 1;
+    round_keys
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, round_key)| {
+            let mut round_key = *round_key;
+            if i != 0 && i != last {
+                inv_mix_columns(&mut round_key);
+            }
+            round_key
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Software,
+    #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+    AesNi,
+}
+
+impl Backend {
+    // The fastest backend the CPU supports
+    pub fn detect() -> Backend {
+        #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+        {
+            if aesni::is_supported() {
+                return Backend::AesNi;
+            }
+        }
+        Backend::Software
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Aes {
+    key_size: KeySize,
+    backend: Backend,
+    round_keys: Vec<Block>,
+    // Only used by hardware backends
+    #[cfg_attr(not(all(feature = "aesni", target_arch = "x86_64")), allow(dead_code))]
+    decryption_round_keys: Vec<Block>,
+}
+
+impl Aes {
+    pub fn new(key: &[u8]) -> Result<Self, InvalidKeyLengthError> {
+        Aes::with_backend(key, Backend::detect())
+    }
+
+    // Use the given backend, falling back to software when the CPU doesn't support it
+    pub fn with_backend(key: &[u8], backend: Backend) -> Result<Self, InvalidKeyLengthError> {
+        let round_keys = expand_key(key)?;
+        let backend = match backend {
+            Backend::Software => Backend::Software,
+            #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+            Backend::AesNi if aesni::is_supported() => Backend::AesNi,
+            #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+            Backend::AesNi => Backend::Software,
+        };
+        let decryption_round_keys = match backend {
+            Backend::Software => vec![],
+            #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+            Backend::AesNi => equivalent_inverse_round_keys(&round_keys),
+        };
+        Ok(Aes {
+            key_size: KeySize::from_key_len(key.len())?,
+            backend,
+            round_keys,
+            decryption_round_keys,
+        })
+    }
+
+    pub fn key_size(&self) -> KeySize {
+        self.key_size
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn round_keys(&self) -> &[Block] {
+        &self.round_keys
+    }
+
+    pub fn encrypt_block(&self, block: &mut Block) {
+        match self.backend {
+            Backend::Software => self.encrypt_block_traced(block, |_, _, _| {}),
+            // Safe: the backend is only picked when the CPU supports AES-NI
+            #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+            Backend::AesNi => unsafe { aesni::encrypt_block(&self.round_keys, block) },
+        }
+    }
+
+    pub fn decrypt_block(&self, block: &mut Block) {
+        match self.backend {
+            Backend::Software => self.decrypt_block_traced(block, |_, _, _| {}),
+            // Safe: the backend is only picked when the CPU supports AES-NI
+            #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+            Backend::AesNi => unsafe { aesni::decrypt_block(&self.decryption_round_keys, block) },
+        }
+    }
+
+    // Encrypt in software whatever the backend, calling `trace` with the round number, the step and
+    // the state after every step
+    pub fn encrypt_block_traced<F>(&self, block: &mut Block, mut trace: F)
+    where
+        F: FnMut(usize, Step, &Block),
+    {
+        let rounds = self.key_size.rounds();
+        add_round_key(block, &self.round_keys[0]);
+        trace(0, Step::AddRoundKey, block);
+        for round in 1..=rounds {
+            sub_bytes(block);
+            trace(round, Step::SubBytes, block);
+            shift_rows(block);
+            trace(round, Step::ShiftRows, block);
+            if round != rounds {
+                mix_columns(block);
+                trace(round, Step::MixColumns, block);
+            }
+            add_round_key(block, &self.round_keys[round]);
+            trace(round, Step::AddRoundKey, block);
+        }
+    }
+
+    // Decrypt in software with the inverse cipher, rounds are numbered as in encryption and count
+    // down
+    pub fn decrypt_block_traced<F>(&self, block: &mut Block, mut trace: F)
+    where
+        F: FnMut(usize, Step, &Block),
+    {
+        let rounds = self.key_size.rounds();
+        add_round_key(block, &self.round_keys[rounds]);
+        trace(rounds, Step::AddRoundKey, block);
+        for round in (0..rounds).rev() {
+            inv_shift_rows(block);
+            trace(round, Step::ShiftRows, block);
+            inv_sub_bytes(block);
+            trace(round, Step::SubBytes, block);
+            add_round_key(block, &self.round_keys[round]);
+            trace(round, Step::AddRoundKey, block);
+            if round != 0 {
+                inv_mix_columns(block);
+                trace(round, Step::MixColumns, block);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenge1::{hex_decode, hex_encode};
+    use std::convert::TryInto;
+
+    fn block(hex: &str) -> Block {
+        hex_decode(hex).unwrap().as_slice().try_into().unwrap()
+    }
+
+    fn aes(key: &str) -> Aes {
+        Aes::new(&hex_decode(key).unwrap()).unwrap()
+    }
+
+    // Encrypt and decrypt back a known answer
+    fn check_known_answer(key: &str, plaintext: &str, ciphertext: &str) {
+        let aes = aes(key);
+        let mut state = block(plaintext);
+        aes.encrypt_block(&mut state);
+        assert_eq!(hex_encode(&state), ciphertext);
+        aes.decrypt_block(&mut state);
+        assert_eq!(hex_encode(&state), plaintext);
+    }
+
+    #[test]
+    fn test_expand_key() {
+        // FIPS-197 appendix A.1
+        let round_keys = expand_key(&block("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        assert_eq!(
+            hex_encode(&round_keys[1]),
+            "a0fafe1788542cb123a339392a6c7605"
+        );
+        assert_eq!(
+            hex_encode(&round_keys[10]),
+            "d014f9a8c9ee2589e13f0cc8b6630ca6"
+        );
+    }
+
+    #[test]
+    fn test_round_steps() {
+        // FIPS-197 appendix B, round 1
+        let mut state = block("193de3bea0f4e22b9ac68d2ae9f84808");
+        sub_bytes(&mut state);
+        assert_eq!(hex_encode(&state), "d42711aee0bf98f1b8b45de51e415230");
+        shift_rows(&mut state);
+        assert_eq!(hex_encode(&state), "d4bf5d30e0b452aeb84111f11e2798e5");
+        mix_columns(&mut state);
+        assert_eq!(hex_encode(&state), "046681e5e0cb199a48f8d37a2806264c");
+        inv_mix_columns(&mut state);
+        inv_shift_rows(&mut state);
+        inv_sub_bytes(&mut state);
+        assert_eq!(hex_encode(&state), "193de3bea0f4e22b9ac68d2ae9f84808");
+    }
+
+    #[test]
+    fn test_encrypt_block_traced() {
+        // FIPS-197 appendix B
+        let aes = aes("2b7e151628aed2a6abf7158809cf4f3c");
+        let mut state = block("3243f6a8885a308d313198a2e0370734");
+        let mut steps = vec![];
+        aes.encrypt_block_traced(&mut state, |round, step, state| {
+            steps.push((round, step, hex_encode(state)))
+        });
+        assert_eq!(hex_encode(&state), "3925841d02dc09fbdc118597196a0b32");
+        assert_eq!(steps.len(), 1 + 9 * 4 + 3);
+        assert_eq!(
+            steps[0],
+            (
+                0,
+                Step::AddRoundKey,
+                "193de3bea0f4e22b9ac68d2ae9f84808".into()
+            )
+        );
+        assert_eq!(
+            steps[4],
+            (
+                1,
+                Step::AddRoundKey,
+                "a49c7ff2689f352b6b5bea43026a5049".into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_expand_key_192_and_256() {
+        // FIPS-197 appendices A.2 and A.3
+        let round_keys =
+            expand_key(&hex_decode("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b").unwrap())
+                .unwrap();
+        assert_eq!(round_keys.len(), 13);
+        assert_eq!(hex_encode(&round_keys[12][12..]), "01002202");
+        let round_keys = expand_key(
+            &hex_decode("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(round_keys.len(), 15);
+        assert_eq!(hex_encode(&round_keys[14][12..]), "706c631e");
+    }
+
+    #[test]
+    fn test_software_backend() {
+        let aes = Aes::with_backend(&[0u8; 16], Backend::Software).unwrap();
+        assert_eq!(aes.backend(), Backend::Software);
+        let mut state = block("f34481ec3cc627bacd5dc3fb08f273e6");
+        aes.encrypt_block(&mut state);
+        assert_eq!(hex_encode(&state), "0336763e966d92595a567cc9ce537f5e");
+    }
+
+    #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+    #[test]
+    fn test_aesni_backend_matches_software() {
+        if !aesni::is_supported() {
+            return;
+        }
+        let mut seed: u8 = 1;
+        let mut next_bytes = |len: usize| -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(73).wrapping_add(41);
+                    seed
+                })
+                .collect()
+        };
+        for key_len in [16, 24, 32].iter() {
+            for _ in 0..32 {
+                let key = next_bytes(*key_len);
+                let hardware = Aes::with_backend(&key, Backend::AesNi).unwrap();
+                let software = Aes::with_backend(&key, Backend::Software).unwrap();
+                assert_eq!(hardware.backend(), Backend::AesNi);
+                let plaintext: Block = next_bytes(BLOCK_SIZE).as_slice().try_into().unwrap();
+                let (mut by_hardware, mut by_software) = (plaintext, plaintext);
+                hardware.encrypt_block(&mut by_hardware);
+                software.encrypt_block(&mut by_software);
+                assert_eq!(by_hardware, by_software);
+                hardware.decrypt_block(&mut by_hardware);
+                assert_eq!(by_hardware, plaintext);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_key_length() {
+        assert_eq!(
+            Aes::new(b"YELLOW SUBMARINE!").unwrap_err(),
+            InvalidKeyLengthError { len: 17 }
+        );
+        assert!(expand_key(&[]).is_err());
+    }
+
+    #[test]
+    fn test_aes128_known_answers() {
+        // FIPS-197 appendix C.1
+        check_known_answer(
+            "000102030405060708090a0b0c0d0e0f",
+            "00112233445566778899aabbccddeeff",
+            "69c4e0d86a7b0430d8cdb78070b4c55a",
+        );
+        // AESAVS GFSbox
+        check_known_answer(
+            "00000000000000000000000000000000",
+            "f34481ec3cc627bacd5dc3fb08f273e6",
+            "0336763e966d92595a567cc9ce537f5e",
+        );
+        assert_eq!(aes(&"00".repeat(16)).key_size(), KeySize::Aes128);
+    }
+
+    #[test]
+    fn test_aes192_known_answers() {
+        // FIPS-197 appendix C.2
+        check_known_answer(
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "00112233445566778899aabbccddeeff",
+            "dda97ca4864cdfe06eaf70a0ec0d7191",
+        );
+        // AESAVS GFSbox
+        check_known_answer(
+            "000000000000000000000000000000000000000000000000",
+            "1b077a6af4b7f98229de786d7516b639",
+            "275cfc0413d8ccb70513c3859b1d0f72",
+        );
+        assert_eq!(aes(&"00".repeat(24)).key_size(), KeySize::Aes192);
+    }
+
+    #[test]
+    fn test_aes256_known_answers() {
+        // FIPS-197 appendix C.3
+        check_known_answer(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff",
+            "8ea2b7ca516745bfeafc49904b496089",
+        );
+        // AESAVS GFSbox
+        check_known_answer(
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "014730f80ac625fe84f026c60bfd547d",
+            "5c9d844ed46f9885085e5d6a4f94c7d7",
+        );
+        assert_eq!(aes(&"00".repeat(32)).key_size(), KeySize::Aes256);
+    }
+}
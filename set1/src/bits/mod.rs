@@ -0,0 +1,177 @@
+/*
+Bit utilities over byte slices
+
+Popcount based hamming distance, bit rotation and bit extraction. Byte slices are handled a 64-bit
+word at a time where possible, with the bytes that don't fill a word handled one by one. Bits are
+numbered from the most significant bit of the first byte.
+*/
+
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+
+const WORD_BYTES: usize = 8;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LengthMismatchError {
+    pub left: usize,
+    pub right: usize,
+}
+
+impl fmt::Display for LengthMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "inputs are of different length: {} and {}",
+            self.left, self.right
+        )
+    }
+}
+
+impl Error for LengthMismatchError {}
+
+fn check_lengths(a: &[u8], b: &[u8]) -> Result<(), LengthMismatchError> {
+    if a.len() != b.len() {
+        return Err(LengthMismatchError {
+            left: a.len(),
+            right: b.len(),
+        });
+    }
+    Ok(())
+}
+
+fn word(bytes: &[u8]) -> u64 {
+    u64::from_ne_bytes(bytes.try_into().unwrap())
+}
+
+// Number of set bits
+pub fn popcount(data: &[u8]) -> u32 {
+    let words = data.chunks_exact(WORD_BYTES);
+    let tail: u32 = words.remainder().iter().map(|b| b.count_ones()).sum();
+    words.map(|w| word(w).count_ones()).sum::<u32>() + tail
+}
+
+// Number of differing bits between two inputs of the same length
+pub fn hamming_distance(a: &[u8], b: &[u8]) -> Result<u32, LengthMismatchError> {
+    check_lengths(a, b)?;
+    let a_words = a.chunks_exact(WORD_BYTES);
+    let b_words = b.chunks_exact(WORD_BYTES);
+    let tail: u32 = a_words
+        .remainder()
+        .iter()
+        .zip(b_words.remainder())
+        .map(|(x, y)| (x ^ y).count_ones())
+        .sum();
+    let words: u32 = a_words
+        .zip(b_words)
+        .map(|(x, y)| (word(x) ^ word(y)).count_ones())
+        .sum();
+    Ok(words + tail)
+}
+
+// Byte-wise XOR of two inputs of the same length
+pub fn xor(a: &[u8], b: &[u8]) -> Result<Vec<u8>, LengthMismatchError> {
+    check_lengths(a, b)?;
+    let mut res = Vec::with_capacity(a.len());
+    let a_words = a.chunks_exact(WORD_BYTES);
+    let b_words = b.chunks_exact(WORD_BYTES);
+    let tail: Vec<u8> = a_words
+        .remainder()
+        .iter()
+        .zip(b_words.remainder())
+        .map(|(x, y)| x ^ y)
+        .collect();
+    for (x, y) in a_words.zip(b_words) {
+        res.extend_from_slice(&(word(x) ^ word(y)).to_ne_bytes());
+    }
+    res.extend(tail);
+    Ok(res)
+}
+
+// Rotate the whole input, seen as one string of bits, left by `bits`
+pub fn rotate_left(data: &[u8], bits: usize) -> Vec<u8> {
+    if data.is_empty() {
+        return vec![];
+    }
+    let len = data.len();
+    let bits = bits % (len * 8);
+    let (byte_shift, bit_shift) = (bits / 8, bits % 8);
+    (0..len)
+        .map(|i| {
+            let high = data[(i + byte_shift) % len];
+            if bit_shift == 0 {
+                return high;
+            }
+            let low = data[(i + byte_shift + 1) % len];
+            (high << bit_shift) | (low >> (8 - bit_shift))
+        })
+        .collect()
+}
+
+// Rotate the whole input, seen as one string of bits, right by `bits`
+pub fn rotate_right(data: &[u8], bits: usize) -> Vec<u8> {
+    if data.is_empty() {
+        return vec![];
+    }
+    let total = data.len() * 8;
+    rotate_left(data, total - bits % total)
+}
+
+pub fn get_bit(data: &[u8], index: usize) -> Option<bool> {
+    data.get(index / 8)
+        .map(|byte| (byte >> (7 - index % 8)) & 1 == 1)
+}
+
+// The `len` bits starting at bit `start` as an integer, first bit most significant. None when the
+// bits don't fit in a u64 or run past the end of the input.
+pub fn extract_bits(data: &[u8], start: usize, len: usize) -> Option<u64> {
+    if len > 64 || start + len > data.len() * 8 {
+        return None;
+    }
+    (start..start + len).try_fold(0u64, |acc, index| {
+        get_bit(data, index).map(|bit| (acc << 1) | bit as u64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hamming_distance() {
+        let res = hamming_distance(b"this is a test", b"wokka wokka!!!");
+        assert_eq!(res, Ok(37));
+        assert_eq!(
+            hamming_distance(b"this is a test", b"wokka"),
+            Err(LengthMismatchError { left: 14, right: 5 })
+        );
+    }
+
+    #[test]
+    fn test_popcount_and_xor() {
+        let data = [0xffu8; 11];
+        assert_eq!(popcount(&data), 88);
+        let res = xor(&data, &[0x0fu8; 11]);
+        assert_eq!(res, Ok(vec![0xf0u8; 11]));
+        assert!(xor(&data, &[0u8; 3]).is_err());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let data = [0b1000_0001, 0b0100_0000];
+        assert_eq!(rotate_left(&data, 1), vec![0b0000_0010, 0b1000_0001]);
+        assert_eq!(rotate_left(&data, 8), vec![0b0100_0000, 0b1000_0001]);
+        assert_eq!(rotate_right(&rotate_left(&data, 5), 5), data.to_vec());
+        assert_eq!(rotate_right(&data, 16), data.to_vec());
+    }
+
+    #[test]
+    fn test_extract_bits() {
+        let data = [0b1010_0000, 0b1111_0000];
+        assert_eq!(get_bit(&data, 0), Some(true));
+        assert_eq!(get_bit(&data, 1), Some(false));
+        assert_eq!(get_bit(&data, 16), None);
+        assert_eq!(extract_bits(&data, 2, 8), Some(0b1000_0011));
+        assert_eq!(extract_bits(&data, 10, 7), None);
+    }
+}
@@ -0,0 +1,163 @@
+/*
+Block cipher abstraction
+
+Modes of operation and the attacks against them only need to encrypt and decrypt one block at a
+time, so they are written against the BlockCipher trait rather than against AES. Besides AES there
+is a toy keyed XOR cipher: it is fast, works with any block size and its output can be checked by
+hand, which keeps mode and oracle tests quick and readable. It is of course not secure at all.
+*/
+
+use crate::aes::{self, Aes};
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+
+pub trait BlockCipher {
+    fn block_size(&self) -> usize;
+
+    // `block` must be exactly block_size() bytes long, implementations panic otherwise
+    fn encrypt_block(&self, block: &mut [u8]);
+
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        aes::BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let block: &mut aes::Block = block.try_into().expect("block is not 16 bytes long");
+        Aes::encrypt_block(self, block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block: &mut aes::Block = block.try_into().expect("block is not 16 bytes long");
+        Aes::decrypt_block(self, block);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct EmptyKeyError;
+
+impl fmt::Display for EmptyKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "key must not be empty")
+    }
+}
+
+impl Error for EmptyKeyError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidIvLengthError {
+    pub len: usize,
+    pub block_size: usize,
+}
+
+impl fmt::Display for InvalidIvLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IV is {} bytes long instead of the block size {}",
+            self.len, self.block_size
+        )
+    }
+}
+
+impl Error for InvalidIvLengthError {}
+
+// Modes of operation take an IV of exactly one block
+pub fn check_iv<C: BlockCipher>(cipher: &C, iv: &[u8]) -> Result<(), InvalidIvLengthError> {
+    if iv.len() != cipher.block_size() {
+        return Err(InvalidIvLengthError {
+            len: iv.len(),
+            block_size: cipher.block_size(),
+        });
+    }
+    Ok(())
+}
+
+// Toy cipher: each block is XORed with the key, so the block size is the key length
+#[derive(Debug, Clone)]
+pub struct XorCipher {
+    key: Vec<u8>,
+}
+
+impl XorCipher {
+    pub fn new(key: &[u8]) -> Result<Self, EmptyKeyError> {
+        if key.is_empty() {
+            return Err(EmptyKeyError);
+        }
+        Ok(XorCipher { key: key.to_vec() })
+    }
+
+    fn xor_key(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), self.key.len(), "block has the wrong size");
+        for (byte, key_byte) in block.iter_mut().zip(&self.key) {
+            *byte ^= key_byte;
+        }
+    }
+}
+
+impl BlockCipher for XorCipher {
+    fn block_size(&self) -> usize {
+        self.key.len()
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        self.xor_key(block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        self.xor_key(block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenge1::hex_encode;
+
+    // Round trip every block through a cipher used only through the trait
+    fn round_trip<C: BlockCipher>(cipher: &C, plaintext: &[u8]) -> Vec<u8> {
+        let mut data = plaintext.to_vec();
+        for block in data.chunks_mut(cipher.block_size()) {
+            cipher.encrypt_block(block);
+        }
+        assert_ne!(data, plaintext);
+        for block in data.chunks_mut(cipher.block_size()) {
+            cipher.decrypt_block(block);
+        }
+        data
+    }
+
+    #[test]
+    fn test_aes_block_cipher() {
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let aes = Aes::new(&key).unwrap();
+        assert_eq!(BlockCipher::block_size(&aes), 16);
+        let mut block = [
+            0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d, 0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37,
+            0x07, 0x34,
+        ];
+        BlockCipher::encrypt_block(&aes, &mut block[..]);
+        assert_eq!(hex_encode(&block), "3925841d02dc09fbdc118597196a0b32");
+        let plaintext = b"YELLOW SUBMARINEyellow submarine";
+        assert_eq!(round_trip(&aes, plaintext), plaintext.to_vec());
+    }
+
+    #[test]
+    fn test_xor_cipher() {
+        let cipher = XorCipher::new(b"\x01\x02\x03\x04").unwrap();
+        assert_eq!(cipher.block_size(), 4);
+        let mut block = *b"AAAA";
+        cipher.encrypt_block(&mut block);
+        assert_eq!(&block, b"@CBE");
+        let plaintext = b"twelve bytes";
+        assert_eq!(round_trip(&cipher, plaintext), plaintext.to_vec());
+        assert_eq!(XorCipher::new(b"").unwrap_err(), EmptyKeyError);
+    }
+}
@@ -0,0 +1,78 @@
+/*
+Block chunking and transposition over byte slices
+
+Split input into blocks of a fixed size, keeping a shorter final block, and transpose the blocks
+into columns: column i holds byte i of every block, which is every byte encrypted by byte i of a
+repeating key. Untransposing interleaves the columns back into the original order.
+*/
+
+// Split into blocks of `size` bytes, the last block holds whatever is left. `size` must not be 0.
+pub fn chunks(data: &[u8], size: usize) -> Vec<&[u8]> {
+    data.chunks(size).collect()
+}
+
+// Always `size` columns; with a ragged final block the first columns are one byte longer than the
+// rest, and when the input is shorter than `size` the last columns are empty. `size` must not be 0.
+pub fn transpose(data: &[u8], size: usize) -> Vec<Vec<u8>> {
+    let mut columns: Vec<Vec<u8>> = (0..size)
+        .map(|_| Vec::with_capacity(data.len() / size + 1))
+        .collect();
+    for block in chunks(data, size) {
+        for (column, byte) in columns.iter_mut().zip(block) {
+            column.push(*byte);
+        }
+    }
+    columns
+}
+
+// Inverse of transpose: take one byte of each column in turn until every column is used up
+pub fn untranspose(columns: &[Vec<u8>]) -> Vec<u8> {
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    let mut data = Vec::with_capacity(columns.iter().map(Vec::len).sum());
+    for row in 0..rows {
+        data.extend(columns.iter().filter_map(|column| column.get(row)));
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_keeps_ragged_tail() {
+        let blocks = chunks(b"hellocruelworld!", 5);
+        assert_eq!(
+            blocks,
+            vec![&b"hello"[..], &b"cruel"[..], &b"world"[..], &b"!"[..]]
+        );
+    }
+
+    #[test]
+    fn test_transpose() {
+        let columns = transpose(b"hellocruelworld!", 5);
+        assert_eq!(
+            columns,
+            vec![
+                b"hcw!".to_vec(),
+                b"ero".to_vec(),
+                b"lur".to_vec(),
+                b"lel".to_vec(),
+                b"old".to_vec()
+            ]
+        );
+        assert_eq!(
+            transpose(b"ab", 3),
+            vec![b"a".to_vec(), b"b".to_vec(), vec![]]
+        );
+    }
+
+    #[test]
+    fn test_untranspose() {
+        let data = b"hellocruelworld!";
+        for size in 1..=data.len() + 1 {
+            assert_eq!(untranspose(&transpose(data, size)), data.to_vec());
+        }
+        assert!(untranspose(&[]).is_empty());
+    }
+}
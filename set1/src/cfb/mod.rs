@@ -0,0 +1,165 @@
+/*
+Cipher feedback (CFB) mode
+
+The previous ciphertext is fed back through the cipher: the shift register starts as the IV, each
+segment of plaintext is XORed with the first bytes of the encrypted register, and the resulting
+ciphertext segment is shifted into the register. Full-block CFB uses segments of a whole block,
+CFB-8 segments of a single byte, which costs a block encryption per byte. Only the cipher's
+encryption is ever used, and the last segment may be short, so there is no padding.
+*/
+
+use crate::block_cipher::{check_iv, BlockCipher, InvalidIvLengthError};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfbError {
+    InvalidIv(InvalidIvLengthError),
+    // Segments are 1 byte up to a whole block
+    InvalidSegmentSize {
+        segment_size: usize,
+        block_size: usize,
+    },
+}
+
+impl fmt::Display for CfbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CfbError::InvalidIv(inner) => write!(f, "{}", inner),
+            CfbError::InvalidSegmentSize {
+                segment_size,
+                block_size,
+            } => write!(
+                f,
+                "invalid segment size {} for a {} byte block",
+                segment_size, block_size
+            ),
+        }
+    }
+}
+
+impl Error for CfbError {}
+
+impl From<InvalidIvLengthError> for CfbError {
+    fn from(err: InvalidIvLengthError) -> Self {
+        CfbError::InvalidIv(err)
+    }
+}
+
+fn cfb<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    segment_size: usize,
+    data: &[u8],
+    encrypt: bool,
+) -> Result<Vec<u8>, CfbError> {
+    check_iv(cipher, iv)?;
+    let block_size = cipher.block_size();
+    if segment_size == 0 || segment_size > block_size {
+        return Err(CfbError::InvalidSegmentSize {
+            segment_size,
+            block_size,
+        });
+    }
+    let mut register = iv.to_vec();
+    let mut output = Vec::with_capacity(data.len());
+    for segment in data.chunks(segment_size) {
+        let mut keystream = register.clone();
+        cipher.encrypt_block(&mut keystream);
+        let out: Vec<u8> = segment.iter().zip(&keystream).map(|(d, k)| d ^ k).collect();
+        let ciphertext = if encrypt { &out[..] } else { segment };
+        register.drain(..segment_size);
+        register.extend_from_slice(ciphertext);
+        output.extend(out);
+    }
+    Ok(output)
+}
+
+// CFB with segments of `segment_size` bytes
+pub fn cfb_encrypt<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    segment_size: usize,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, CfbError> {
+    cfb(cipher, iv, segment_size, plaintext, true)
+}
+
+pub fn cfb_decrypt<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    segment_size: usize,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CfbError> {
+    cfb(cipher, iv, segment_size, ciphertext, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenge1::{hex_decode, hex_encode};
+    use crate::challenge7::aes_128;
+
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const IV: &str = "000102030405060708090a0b0c0d0e0f";
+
+    #[test]
+    fn test_cfb128_known_answer() {
+        let aes = aes_128(&hex_decode(KEY).unwrap()).unwrap();
+        let iv = hex_decode(IV).unwrap();
+        let plaintext = hex_decode(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        )
+        .unwrap();
+        let res = cfb_encrypt(&aes, &iv, 16, &plaintext);
+        assert!(res.is_ok());
+        let ciphertext = res.unwrap();
+        assert_eq!(
+            hex_encode(&ciphertext),
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+             26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6"
+        );
+        assert_eq!(cfb_decrypt(&aes, &iv, 16, &ciphertext).unwrap(), plaintext);
+        let res = cfb_encrypt(&aes, &iv, 16, &plaintext[..25]);
+        assert_eq!(res.unwrap(), ciphertext[..25].to_vec());
+    }
+
+    #[test]
+    fn test_cfb8_known_answer() {
+        let aes = aes_128(&hex_decode(KEY).unwrap()).unwrap();
+        let iv = hex_decode(IV).unwrap();
+        let plaintext = hex_decode("6bc1bee22e409f96e93d7e117393172aae2d").unwrap();
+        let res = cfb_encrypt(&aes, &iv, 1, &plaintext);
+        assert!(res.is_ok());
+        let ciphertext = res.unwrap();
+        assert_eq!(
+            hex_encode(&ciphertext),
+            "3b79424c9c0dd436bace9e0ed4586a4f32b9"
+        );
+        assert_eq!(cfb_decrypt(&aes, &iv, 1, &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_cfb_errors() {
+        let aes = aes_128(b"YELLOW SUBMARINE").unwrap();
+        assert_eq!(
+            cfb_encrypt(&aes, &[0u8; 16], 0, b"data"),
+            Err(CfbError::InvalidSegmentSize {
+                segment_size: 0,
+                block_size: 16
+            })
+        );
+        assert_eq!(
+            cfb_decrypt(&aes, &[0u8; 16], 17, b"data"),
+            Err(CfbError::InvalidSegmentSize {
+                segment_size: 17,
+                block_size: 16
+            })
+        );
+        assert!(matches!(
+            cfb_encrypt(&aes, &[0u8; 15], 1, b"data"),
+            Err(CfbError::InvalidIv(_))
+        ));
+    }
+}
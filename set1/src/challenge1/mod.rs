@@ -88,6 +88,19 @@ pub fn base64_encode(input: &str) -> Result<String, InvalidHexCharFoundError> {
     Ok(result)
 }
 
+// Decode hex string into raw bytes, a trailing odd nibble is ignored
+pub fn hex_decode(input: &str) -> Result<Vec<u8>, InvalidHexCharFoundError> {
+    let binary_input = hex_to_binary(input)?;
+    Ok((0..binary_input.len() / 8)
+        .map(|i| u8::from_str_radix(&binary_input[i * 8..i * 8 + 8], 2).unwrap())
+        .collect())
+}
+
+// Encode raw bytes as lowercase hex string
+pub fn hex_encode(input: &[u8]) -> String {
+    input.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,4 +134,14 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), expected_output)
     }
+
+    #[test]
+    fn test_hex_decode_and_encode() {
+        let res = hex_decode("49276D");
+        assert!(res.is_ok());
+        let bytes = res.unwrap();
+        assert_eq!(bytes, vec![0x49, 0x27, 0x6d]);
+        assert_eq!(hex_encode(&bytes), String::from("49276d"));
+        assert!(hex_decode("4g").is_err());
+    }
 }
@@ -0,0 +1,430 @@
+/*
+Implement CBC mode
+
+CBC mode is a block cipher mode that allows us to encrypt irregularly-sized messages, despite the
+fact that a block cipher natively only transforms individual blocks.
+
+In CBC mode, each ciphertext block is added to the next plaintext block before the next call to the
+cipher core.
+
+The first plaintext block, which has no associated previous ciphertext block, is added to a "fake
+0th ciphertext block" called the initialization vector, or IV.
+
+Implement CBC mode by hand by taking the ECB function you wrote earlier, making it encrypt instead
+of decrypt (verify this by decrypting whatever you encrypt to test), and using your XOR function from
+the previous exercise to combine them.
+
+The file here is intelligible (somewhat) when CBC decrypted against "YELLOW SUBMARINE" with an IV of
+all ASCII 0 (\x00\x00\x00 &c)
+
+Don't cheat.
+Do not use OpenSSL's CBC code to do CBC mode, even to verify your results. What's the point of even
+doing this stuff if you aren't going to learn from it?
+
+Padding is up to the caller, see challenge9. Ciphertext stealing avoids it altogether: the last
+plaintext block is zero padded, and only as many bytes of the second to last ciphertext block as the
+last plaintext block holds are kept, the rest can be recovered from decrypting the last block. The
+three variants of the SP 800-38A addendum differ in the order of the last two ciphertext blocks.
+*/
+
+use crate::aes::InvalidKeyLengthError;
+use crate::block_cipher::{check_iv, BlockCipher, InvalidIvLengthError};
+use crate::challenge2::fixed_xor_bytes;
+use crate::challenge6::base64_decode_bytes;
+use crate::challenge7::{aes_128, ecb_decrypt, ecb_encrypt};
+use crate::challenge9::{NoPadding, Padding, PaddingError, Pkcs7, ZeroBytes};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum CbcError {
+    InvalidIv(InvalidIvLengthError),
+    InvalidLength { len: usize, block_size: usize },
+    Padding(PaddingError),
+    InvalidKey(InvalidKeyLengthError),
+    InvalidBase64Char(char),
+}
+
+impl fmt::Display for CbcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CbcError::InvalidIv(inner) => write!(f, "{}", inner),
+            CbcError::InvalidLength { len, block_size } => write!(
+                f,
+                "length {} is not a multiple of the block size {}",
+                len, block_size
+            ),
+            CbcError::Padding(inner) => write!(f, "{}", inner),
+            CbcError::InvalidKey(inner) => write!(f, "{}", inner),
+            CbcError::InvalidBase64Char(ch) => write!(f, "invalid base64 char {:?}", ch),
+        }
+    }
+}
+
+impl Error for CbcError {}
+
+impl From<PaddingError> for CbcError {
+    fn from(err: PaddingError) -> Self {
+        match err {
+            PaddingError::InvalidLength { len, block_size } => {
+                CbcError::InvalidLength { len, block_size }
+            }
+            _ => CbcError::Padding(err),
+        }
+    }
+}
+
+impl From<InvalidIvLengthError> for CbcError {
+    fn from(err: InvalidIvLengthError) -> Self {
+        CbcError::InvalidIv(err)
+    }
+}
+
+impl From<InvalidKeyLengthError> for CbcError {
+    fn from(err: InvalidKeyLengthError) -> Self {
+        CbcError::InvalidKey(err)
+    }
+}
+
+fn check_lengths<C: BlockCipher>(cipher: &C, iv: &[u8], len: usize) -> Result<(), CbcError> {
+    check_iv(cipher, iv)?;
+    let block_size = cipher.block_size();
+    if !len.is_multiple_of(block_size) {
+        return Err(CbcError::InvalidLength { len, block_size });
+    }
+    Ok(())
+}
+
+// Blocks and IV are always block sized here, so neither the XOR nor single block ECB can fail
+fn xor_block(a: &[u8], b: &[u8]) -> Vec<u8> {
+    fixed_xor_bytes(a, b).expect("blocks of the same size")
+}
+
+fn ecb_block<C: BlockCipher>(cipher: &C, block: &[u8], encrypt: bool) -> Vec<u8> {
+    let res = if encrypt {
+        ecb_encrypt(cipher, block, &NoPadding)
+    } else {
+        ecb_decrypt(cipher, block, &NoPadding)
+    };
+    res.expect("a whole block")
+}
+
+// CBC without padding, the plaintext must be a multiple of the block size
+pub fn cbc_encrypt_blocks<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, CbcError> {
+    let block_size = cipher.block_size();
+    check_lengths(cipher, iv, plaintext.len())?;
+    let mut ciphertext = Vec::with_capacity(plaintext.len());
+    let mut previous = iv.to_vec();
+    for block in plaintext.chunks(block_size) {
+        previous = ecb_block(cipher, &xor_block(block, &previous), true);
+        ciphertext.extend_from_slice(&previous);
+    }
+    Ok(ciphertext)
+}
+
+// CBC without padding, the ciphertext must be a multiple of the block size
+pub fn cbc_decrypt_blocks<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CbcError> {
+    let block_size = cipher.block_size();
+    check_lengths(cipher, iv, ciphertext.len())?;
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    let mut previous = iv;
+    for block in ciphertext.chunks(block_size) {
+        plaintext.extend(xor_block(&ecb_block(cipher, block, false), previous));
+        previous = block;
+    }
+    Ok(plaintext)
+}
+
+pub fn cbc_encrypt<C: BlockCipher, P: Padding + ?Sized>(
+    cipher: &C,
+    iv: &[u8],
+    plaintext: &[u8],
+    padding: &P,
+) -> Result<Vec<u8>, CbcError> {
+    cbc_encrypt_blocks(cipher, iv, &padding.pad(plaintext, cipher.block_size())?)
+}
+
+pub fn cbc_decrypt<C: BlockCipher, P: Padding + ?Sized>(
+    cipher: &C,
+    iv: &[u8],
+    ciphertext: &[u8],
+    padding: &P,
+) -> Result<Vec<u8>, CbcError> {
+    let mut plaintext = cbc_decrypt_blocks(cipher, iv, ciphertext)?;
+    let len = padding.unpad(&plaintext, cipher.block_size())?.len();
+    plaintext.truncate(len);
+    Ok(plaintext)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtsVariant {
+    // The truncated second to last block comes first
+    Cs1,
+    // Like CS3 when the last block is partial, plain CBC otherwise
+    Cs2,
+    // The last two blocks are always swapped (Kerberos, RFC 3962)
+    Cs3,
+}
+
+fn check_cts_length(len: usize, block_size: usize) -> Result<(), CbcError> {
+    if len < block_size {
+        return Err(CbcError::InvalidLength { len, block_size });
+    }
+    Ok(())
+}
+
+fn swaps_last_blocks(variant: CtsVariant, partial: bool) -> bool {
+    variant == CtsVariant::Cs3 || (variant == CtsVariant::Cs2 && partial)
+}
+
+// CBC with ciphertext stealing, the ciphertext is as long as the plaintext. The plaintext must be
+// at least a block long.
+pub fn cbc_cts_encrypt<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    plaintext: &[u8],
+    variant: CtsVariant,
+) -> Result<Vec<u8>, CbcError> {
+    let block_size = cipher.block_size();
+    check_cts_length(plaintext.len(), block_size)?;
+    let mut ciphertext = cbc_encrypt_blocks(cipher, iv, &ZeroBytes.pad(plaintext, block_size)?)?;
+    if ciphertext.len() == block_size {
+        return Ok(ciphertext);
+    }
+    let last_len = plaintext.len() - (ciphertext.len() - block_size);
+    let last = ciphertext.split_off(ciphertext.len() - block_size);
+    ciphertext.truncate(ciphertext.len() - block_size + last_len);
+    if swaps_last_blocks(variant, last_len < block_size) {
+        let stolen = ciphertext.split_off(ciphertext.len() - last_len);
+        ciphertext.extend(last);
+        ciphertext.extend(stolen);
+    } else {
+        ciphertext.extend(last);
+    }
+    Ok(ciphertext)
+}
+
+pub fn cbc_cts_decrypt<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    ciphertext: &[u8],
+    variant: CtsVariant,
+) -> Result<Vec<u8>, CbcError> {
+    let block_size = cipher.block_size();
+    check_cts_length(ciphertext.len(), block_size)?;
+    if ciphertext.len() == block_size {
+        return cbc_decrypt_blocks(cipher, iv, ciphertext);
+    }
+    let head_len = (ciphertext.len() - 1) / block_size * block_size - block_size;
+    let last_len = ciphertext.len() - head_len - block_size;
+    let (head, tail) = ciphertext.split_at(head_len);
+    // Back to CS1 order: the stolen bytes, then the last block
+    let (stolen, last) = if swaps_last_blocks(variant, last_len < block_size) {
+        let (last, stolen) = tail.split_at(block_size);
+        (stolen, last)
+    } else {
+        tail.split_at(last_len)
+    };
+    // The last block decrypts to the zero padded plaintext XOR the whole second to last block,
+    // whose missing bytes are the padding bytes
+    let decrypted_last = ecb_block(cipher, last, false);
+    let mut blocks = head.to_vec();
+    blocks.extend_from_slice(stolen);
+    blocks.extend_from_slice(&decrypted_last[last_len..]);
+    let mut plaintext = cbc_decrypt_blocks(cipher, iv, &blocks)?;
+    plaintext.extend(xor_block(&decrypted_last[..last_len], stolen));
+    Ok(plaintext)
+}
+
+// Decrypt base64 encoded AES-128-CBC ciphertext with PKCS#7 padding, as given in the challenge
+pub fn decrypt_base64(encoded: &str, key: &[u8], iv: &[u8]) -> Result<Vec<u8>, CbcError> {
+    let ciphertext =
+        base64_decode_bytes(encoded).map_err(|err| CbcError::InvalidBase64Char(err.ch))?;
+    cbc_decrypt(&aes_128(key)?, iv, &ciphertext, &Pkcs7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_cipher::XorCipher;
+    use crate::challenge1::{hex_decode, hex_encode};
+    use crate::challenge9::Iso7816;
+    use std::fs;
+
+    #[test]
+    fn test_cbc_known_answer() {
+        let aes = aes_128(&hex_decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap()).unwrap();
+        let iv = hex_decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let plaintext = hex_decode(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        )
+        .unwrap();
+        let res = cbc_encrypt_blocks(&aes, &iv, &plaintext);
+        assert!(res.is_ok());
+        let ciphertext = res.unwrap();
+        assert_eq!(
+            hex_encode(&ciphertext),
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2\
+             73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7"
+        );
+        let res = cbc_decrypt_blocks(&aes, &iv, &ciphertext);
+        assert_eq!(res.unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_cbc_chains_blocks() {
+        // With an all zero key every ciphertext block is the plaintext XOR the previous one
+        let cipher = XorCipher::new(&[0u8; 4]).unwrap();
+        let res = cbc_encrypt(&cipher, b"\x01\x02\x03\x04", b"aaaaaaaa", &Pkcs7);
+        assert!(res.is_ok());
+        let ciphertext = res.unwrap();
+        assert_eq!(&ciphertext[..4], b"`cbe");
+        assert_eq!(&ciphertext[4..8], b"\x01\x02\x03\x04");
+        assert_eq!(&ciphertext[8..], b"\x05\x06\x07\x00");
+        let res = cbc_decrypt(&cipher, b"\x01\x02\x03\x04", &ciphertext, &Pkcs7);
+        assert_eq!(res.unwrap(), b"aaaaaaaa".to_vec());
+    }
+
+    #[test]
+    fn test_cbc_round_trip() {
+        let aes = aes_128(b"YELLOW SUBMARINE").unwrap();
+        let iv = [0u8; 16];
+        for len in 0..50 {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+            let ciphertext = cbc_encrypt(&aes, &iv, &plaintext, &Pkcs7).unwrap();
+            assert_eq!(ciphertext.len(), (len / 16 + 1) * 16);
+            assert_eq!(
+                cbc_decrypt(&aes, &iv, &ciphertext, &Pkcs7).unwrap(),
+                plaintext
+            );
+        }
+    }
+
+    #[test]
+    fn test_cbc_other_padding() {
+        let aes = aes_128(b"YELLOW SUBMARINE").unwrap();
+        let iv = [1u8; 16];
+        let ciphertext = cbc_encrypt(&aes, &iv, b"data\0", &Iso7816).unwrap();
+        let res = cbc_decrypt(&aes, &iv, &ciphertext, &Iso7816);
+        assert_eq!(res.unwrap(), b"data\0".to_vec());
+        assert!(matches!(
+            cbc_encrypt(&aes, &iv, b"data", &NoPadding),
+            Err(CbcError::InvalidLength {
+                len: 4,
+                block_size: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn test_cbc_cts_known_answer() {
+        // RFC 3962, which uses CS3
+        let aes = aes_128(b"chicken teriyaki").unwrap();
+        let iv = [0u8; 16];
+        let plaintext = b"I would like the General Gau's Chicken, please, ";
+        for (len, expected) in [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (
+                31,
+                "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+            ),
+            (
+                32,
+                "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+            ),
+        ]
+        .iter()
+        {
+            let res = cbc_cts_encrypt(&aes, &iv, &plaintext[..*len], CtsVariant::Cs3);
+            assert!(res.is_ok());
+            let ciphertext = res.unwrap();
+            assert_eq!(hex_encode(&ciphertext), *expected);
+            let res = cbc_cts_decrypt(&aes, &iv, &ciphertext, CtsVariant::Cs3);
+            assert_eq!(res.unwrap(), plaintext[..*len].to_vec());
+        }
+    }
+
+    #[test]
+    fn test_cbc_cts_variants() {
+        let aes = aes_128(b"YELLOW SUBMARINE").unwrap();
+        let iv = [3u8; 16];
+        let plaintext: Vec<u8> = (0..64).collect();
+        for len in 16..=64 {
+            let plaintext = &plaintext[..len];
+            let cs3 = cbc_cts_encrypt(&aes, &iv, plaintext, CtsVariant::Cs3).unwrap();
+            for &variant in [CtsVariant::Cs1, CtsVariant::Cs2, CtsVariant::Cs3].iter() {
+                let ciphertext = cbc_cts_encrypt(&aes, &iv, plaintext, variant).unwrap();
+                assert_eq!(ciphertext.len(), len);
+                let res = cbc_cts_decrypt(&aes, &iv, &ciphertext, variant);
+                assert_eq!(res.unwrap(), plaintext.to_vec());
+            }
+            let cbc = cbc_encrypt_blocks(&aes, &iv, &ZeroBytes.pad(plaintext, 16).unwrap());
+            let cbc = cbc.unwrap();
+            let cs1 = cbc_cts_encrypt(&aes, &iv, plaintext, CtsVariant::Cs1).unwrap();
+            let cs2 = cbc_cts_encrypt(&aes, &iv, plaintext, CtsVariant::Cs2).unwrap();
+            if len % 16 == 0 {
+                // Whole blocks: CS1 and CS2 are plain CBC, CS3 still swaps
+                assert_eq!(cs1, cbc);
+                assert_eq!(cs2, cbc);
+                assert_eq!(cs3 == cbc, len == 16);
+            } else {
+                assert_eq!(cs2, cs3);
+                assert_ne!(cs1, cs3);
+            }
+        }
+        assert!(matches!(
+            cbc_cts_encrypt(&aes, &iv, b"short", CtsVariant::Cs1),
+            Err(CbcError::InvalidLength {
+                len: 5,
+                block_size: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn test_cbc_errors() {
+        let cipher = XorCipher::new(b"key!").unwrap();
+        assert!(matches!(
+            cbc_encrypt(&cipher, b"iv", b"data", &Pkcs7),
+            Err(CbcError::InvalidIv(InvalidIvLengthError {
+                len: 2,
+                block_size: 4
+            }))
+        ));
+        assert!(matches!(
+            cbc_decrypt(&cipher, b"iv!!", b"datum", &Pkcs7),
+            Err(CbcError::InvalidLength {
+                len: 5,
+                block_size: 4
+            })
+        ));
+        assert!(matches!(
+            cbc_decrypt(&cipher, b"iv!!", b"data", &Pkcs7),
+            Err(CbcError::Padding(_))
+        ));
+        assert!(matches!(
+            decrypt_base64("", b"short key", &[0u8; 16]),
+            Err(CbcError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_challenge_file() {
+        let encoded = fs::read_to_string("src/challenge10/10.txt").unwrap();
+        let res = decrypt_base64(&encoded, b"YELLOW SUBMARINE", &[0u8; 16]);
+        assert!(res.is_ok());
+        let plaintext = String::from_utf8(res.unwrap()).unwrap();
+        assert_eq!(plaintext.len(), 2876);
+        assert!(plaintext.starts_with("I'm back and I'm ringin' the bell"));
+        assert!(plaintext.ends_with("Play that funky music \n"));
+    }
+}
@@ -0,0 +1,157 @@
+/*
+An ECB/CBC detection oracle
+
+Now that you have ECB and CBC working:
+
+Write a function to generate a random AES key; that's just 16 random bytes.
+
+Write a function that encrypts data under an unknown key --- that is, a function that generates a
+random key and encrypts under it.
+
+The function should look like:
+
+encryption_oracle(your-input)
+=> [MEANINGLESS JIBBER JABBER]
+
+Under the hood, have the function append 5-10 bytes (count chosen randomly) before the plaintext and
+5-10 bytes after the plaintext.
+
+Now, have the function choose to encrypt under ECB 1/2 the time, and under CBC the other half (just
+use random IVs each time for CBC). Use rand(2) to decide which to use.
+
+Detect the block cipher mode the function is using each time. You should end up with a piece of code
+that, pointed at a block box that might be encrypting ECB or CBC, tells you which one is happening.
+*/
+
+use crate::aes::{Block, BLOCK_SIZE};
+use crate::challenge10::cbc_encrypt;
+use crate::challenge7::{aes_128, ecb_encrypt};
+use crate::challenge8::block_repetition;
+use crate::challenge9::Pkcs7;
+use crate::oracle::{Answer, Oracle};
+use rand::Rng;
+
+// Enough identical bytes for two identical whole blocks whatever the 5 to 10 byte prefix
+const DETECTION_INPUT_LEN: usize = 3 * BLOCK_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Ecb,
+    Cbc,
+}
+
+pub fn random_aes_key<R: Rng>(rng: &mut R) -> Block {
+    rng.gen()
+}
+
+// Encrypts under a new random key and a random mode every time
+#[derive(Debug)]
+pub struct EncryptionOracle<R> {
+    rng: R,
+    last_mode: Option<Mode>,
+}
+
+impl<R: Rng> EncryptionOracle<R> {
+    pub fn new(rng: R) -> Self {
+        EncryptionOracle {
+            rng,
+            last_mode: None,
+        }
+    }
+
+    // The mode behind the last answer, to check a detector against. Not for attackers.
+    pub fn last_mode(&self) -> Option<Mode> {
+        self.last_mode
+    }
+}
+
+impl<R: Rng> Oracle for EncryptionOracle<R> {
+    type Output = Vec<u8>;
+
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let aes = aes_128(&random_aes_key(&mut self.rng)).expect("16 byte key");
+        let prefix_len = self.rng.gen_range(5, 11);
+        let suffix_len = self.rng.gen_range(5, 11);
+        let mut plaintext: Vec<u8> = (0..prefix_len).map(|_| self.rng.gen()).collect();
+        plaintext.extend_from_slice(input);
+        plaintext.extend((0..suffix_len).map(|_| self.rng.gen::<u8>()));
+        // PKCS#7 always fills the last block, so neither mode can fail
+        let (mode, ciphertext) = if self.rng.gen() {
+            let ciphertext = ecb_encrypt(&aes, &plaintext, &Pkcs7).expect("padded plaintext");
+            (Mode::Ecb, ciphertext)
+        } else {
+            let iv: Block = self.rng.gen();
+            let ciphertext = cbc_encrypt(&aes, &iv, &plaintext, &Pkcs7).expect("padded plaintext");
+            (Mode::Cbc, ciphertext)
+        };
+        self.last_mode = Some(mode);
+        ciphertext
+    }
+}
+
+pub fn encryption_oracle(input: &[u8]) -> Vec<u8> {
+    EncryptionOracle::new(rand::thread_rng()).query(input)
+}
+
+// One query: ECB turns identical plaintext blocks into identical ciphertext blocks. Fails only when
+// the oracle refuses to answer.
+pub fn detect_mode<O>(oracle: &mut O) -> Result<Mode, <O::Output as Answer>::Error>
+where
+    O: Oracle,
+    O::Output: Answer<Value = Vec<u8>>,
+{
+    let ciphertext = oracle.query(&[b'A'; DETECTION_INPUT_LEN]).into_result()?;
+    if block_repetition(&ciphertext, BLOCK_SIZE).is_likely_ecb() {
+        Ok(Mode::Ecb)
+    } else {
+        Ok(Mode::Cbc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle::{Accounting, BudgetExhaustedError};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_encryption_oracle() {
+        let ciphertext = encryption_oracle(b"YELLOW SUBMARINE");
+        assert!(ciphertext.len() == 32 || ciphertext.len() == 48);
+        let mut oracle = EncryptionOracle::new(StdRng::seed_from_u64(11));
+        assert_eq!(oracle.last_mode(), None);
+        let modes: Vec<Mode> = (0..40)
+            .map(|_| {
+                oracle.query(b"");
+                oracle.last_mode().unwrap()
+            })
+            .collect();
+        assert!(modes.contains(&Mode::Ecb));
+        assert!(modes.contains(&Mode::Cbc));
+    }
+
+    #[test]
+    fn test_detect_mode() {
+        let mut oracle = EncryptionOracle::new(StdRng::seed_from_u64(11));
+        for _ in 0..200 {
+            let detected = detect_mode(&mut oracle).unwrap();
+            assert_eq!(Some(detected), oracle.last_mode());
+        }
+    }
+
+    #[test]
+    fn test_detect_mode_takes_one_query() {
+        let oracle = EncryptionOracle::new(StdRng::seed_from_u64(12));
+        let mut accounting = Accounting::new(oracle).with_budget(1).recording();
+        let res = detect_mode(&mut accounting);
+        assert!(res.is_ok());
+        assert_eq!(accounting.queries(), 1);
+        assert_eq!(accounting.transcript()[0].input, vec![b'A'; 48]);
+        assert_eq!(
+            detect_mode(&mut accounting),
+            Err(BudgetExhaustedError { budget: 1 })
+        );
+        assert_eq!(res.ok(), accounting.into_inner().last_mode());
+    }
+}
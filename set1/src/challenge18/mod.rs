@@ -0,0 +1,388 @@
+/*
+Implement CTR, the stream cipher mode
+
+The string:
+
+L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==
+
+... decrypts to something approximating English in CTR mode, which is an AES block cipher mode that
+turns AES into a stream cipher, with the following parameters:
+
+      key=YELLOW SUBMARINE
+      nonce=0
+      format=64 bit unsigned little endian nonce,
+             64 bit little endian block count (byte count / 16)
+
+CTR mode is very simple.
+
+Instead of encrypting the plaintext, CTR mode encrypts a running counter, producing a 16 byte block
+of keystream, which is XOR'd against the plaintext.
+
+For instance, for the first 16 bytes of a message with these parameters:
+
+keystream = AES("YELLOW SUBMARINE",
+                "\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00")
+
+... for the next 16 bytes of a message:
+
+keystream = AES("YELLOW SUBMARINE",
+                "\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00")
+
+CTR mode does not require padding; when you run out of plaintext, you just stop XOR'ing keystream
+and stop generating keystream.
+
+Decryption is identical to encryption. Generate the same keystream, XOR, and you're done.
+
+Decrypt the string at the top of this function, then use your CTR function to encrypt and decrypt
+other things.
+
+The counter block is the nonce followed by the counter. The nonce and counter sizes and the counter
+endianness are up to the caller, so both the layout above and the big-endian counter block of NIST
+SP 800-38A (or GCM's 96-bit nonce and 32-bit counter) can be used.
+*/
+
+use crate::block_cipher::BlockCipher;
+use crate::challenge5::keystream_xor;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterLayout {
+    pub nonce_len: usize,
+    // At most 16 bytes, the counter wraps around at the end of its width
+    pub counter_len: usize,
+    pub endianness: Endianness,
+}
+
+impl CounterLayout {
+    // 64-bit nonce followed by a 64-bit little-endian block count
+    pub const CRYPTOPALS: CounterLayout = CounterLayout {
+        nonce_len: 8,
+        counter_len: 8,
+        endianness: Endianness::Little,
+    };
+
+    // The whole 128-bit counter block is a big-endian integer
+    pub const NIST: CounterLayout = CounterLayout {
+        nonce_len: 0,
+        counter_len: 16,
+        endianness: Endianness::Big,
+    };
+
+    // 96-bit nonce followed by a 32-bit big-endian counter
+    pub const GCM: CounterLayout = CounterLayout {
+        nonce_len: 12,
+        counter_len: 4,
+        endianness: Endianness::Big,
+    };
+
+    fn counter_mask(&self) -> u128 {
+        u128::MAX >> (128 - 8 * self.counter_len)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CtrError {
+    InvalidLayout {
+        layout: CounterLayout,
+        block_size: usize,
+    },
+    InvalidNonceLength {
+        len: usize,
+        expected: usize,
+    },
+    CounterTooLarge {
+        counter: u128,
+        counter_len: usize,
+    },
+}
+
+impl fmt::Display for CtrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CtrError::InvalidLayout { layout, block_size } => write!(
+                f,
+                "{} byte nonce and {} byte counter don't make a {} byte block",
+                layout.nonce_len, layout.counter_len, block_size
+            ),
+            CtrError::InvalidNonceLength { len, expected } => {
+                write!(f, "nonce is {} bytes long instead of {}", len, expected)
+            }
+            CtrError::CounterTooLarge {
+                counter,
+                counter_len,
+            } => write!(
+                f,
+                "counter {} doesn't fit in {} bytes",
+                counter, counter_len
+            ),
+        }
+    }
+}
+
+impl Error for CtrError {}
+
+// The keystream: encrypted counter blocks, one byte at a time. It never ends, the counter wraps.
+#[derive(Debug, Clone)]
+pub struct Keystream<'a, C> {
+    cipher: &'a C,
+    layout: CounterLayout,
+    nonce: Vec<u8>,
+    initial_counter: u128,
+    counter: u128,
+    block: Vec<u8>,
+    // Next byte of `block` to hand out, a full block means it has to be refilled
+    position: usize,
+}
+
+impl<'a, C: BlockCipher> Keystream<'a, C> {
+    pub fn new(
+        cipher: &'a C,
+        layout: CounterLayout,
+        nonce: &[u8],
+        initial_counter: u128,
+    ) -> Result<Self, CtrError> {
+        let block_size = cipher.block_size();
+        if layout.counter_len == 0
+            || layout.counter_len > 16
+            || layout.nonce_len + layout.counter_len != block_size
+        {
+            return Err(CtrError::InvalidLayout { layout, block_size });
+        }
+        if nonce.len() != layout.nonce_len {
+            return Err(CtrError::InvalidNonceLength {
+                len: nonce.len(),
+                expected: layout.nonce_len,
+            });
+        }
+        if initial_counter & !layout.counter_mask() != 0 {
+            return Err(CtrError::CounterTooLarge {
+                counter: initial_counter,
+                counter_len: layout.counter_len,
+            });
+        }
+        Ok(Keystream {
+            cipher,
+            layout,
+            nonce: nonce.to_vec(),
+            initial_counter,
+            counter: initial_counter,
+            block: vec![0u8; block_size],
+            position: block_size,
+        })
+    }
+
+    // Nonce followed by the counter in the layout's byte order
+    pub fn counter_block(&self) -> Vec<u8> {
+        let mut block = self.nonce.clone();
+        let counter_len = self.layout.counter_len;
+        match self.layout.endianness {
+            Endianness::Little => {
+                block.extend_from_slice(&self.counter.to_le_bytes()[..counter_len])
+            }
+            Endianness::Big => {
+                block.extend_from_slice(&self.counter.to_be_bytes()[16 - counter_len..])
+            }
+        }
+        block
+    }
+
+    // Continue from byte `offset` of the keystream. Only the block holding that byte is encrypted,
+    // the counter of any block is the initial counter plus the block index.
+    pub fn seek(&mut self, offset: usize) {
+        let block_size = self.block.len();
+        let blocks = (offset / block_size) as u128;
+        self.counter = self.initial_counter.wrapping_add(blocks) & self.layout.counter_mask();
+        self.position = block_size;
+        if !offset.is_multiple_of(block_size) {
+            self.refill();
+            self.position = offset % block_size;
+        }
+    }
+
+    fn refill(&mut self) {
+        self.block = self.counter_block();
+        self.cipher.encrypt_block(&mut self.block);
+        self.counter = self.counter.wrapping_add(1) & self.layout.counter_mask();
+        self.position = 0;
+    }
+}
+
+impl<'a, C: BlockCipher> Iterator for Keystream<'a, C> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.position == self.block.len() {
+            self.refill();
+        }
+        self.position += 1;
+        Some(self.block[self.position - 1])
+    }
+}
+
+// Encrypts and decrypts alike
+pub fn ctr_apply<C: BlockCipher>(
+    cipher: &C,
+    layout: CounterLayout,
+    nonce: &[u8],
+    initial_counter: u128,
+    data: &[u8],
+) -> Result<Vec<u8>, CtrError> {
+    let keystream = Keystream::new(cipher, layout, nonce, initial_counter)?;
+    Ok(keystream_xor(data, keystream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_cipher::XorCipher;
+    use crate::challenge1::{hex_decode, hex_encode};
+    use crate::challenge6::base64_decode_bytes;
+    use crate::challenge7::aes_128;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_challenge18() {
+        let ciphertext = base64_decode_bytes(
+            "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+        )
+        .unwrap();
+        let aes = aes_128(b"YELLOW SUBMARINE").unwrap();
+        let res = ctr_apply(&aes, CounterLayout::CRYPTOPALS, &[0u8; 8], 0, &ciphertext);
+        assert!(res.is_ok());
+        assert_eq!(
+            String::from_utf8(res.unwrap()).unwrap(),
+            "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+        );
+    }
+
+    #[test]
+    fn test_ctr_known_answer() {
+        let aes = aes_128(&hex_decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap()).unwrap();
+        let plaintext = hex_decode(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        )
+        .unwrap();
+        let counter = 0xf0f1f2f3f4f5f6f7f8f9fafbfcfdfeff;
+        let res = ctr_apply(&aes, CounterLayout::NIST, &[], counter, &plaintext);
+        assert!(res.is_ok());
+        let ciphertext = res.unwrap();
+        assert_eq!(
+            hex_encode(&ciphertext),
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+             5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee"
+        );
+        let res = ctr_apply(&aes, CounterLayout::NIST, &[], counter, &ciphertext);
+        assert_eq!(res.unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_counter_block_layout() {
+        let cipher = XorCipher::new(&[0u8; 8]).unwrap();
+        let little = CounterLayout {
+            nonce_len: 5,
+            counter_len: 3,
+            endianness: Endianness::Little,
+        };
+        let mut keystream = Keystream::new(&cipher, little, b"nonce", 0x0102ff).unwrap();
+        assert_eq!(keystream.counter_block(), b"nonce\xff\x02\x01".to_vec());
+        keystream.nth(7);
+        assert_eq!(keystream.counter_block(), b"nonce\x00\x03\x01".to_vec());
+        let big = CounterLayout {
+            endianness: Endianness::Big,
+            ..little
+        };
+        // A zero key makes the keystream the counter blocks themselves, and the counter wraps
+        let keystream = Keystream::new(&cipher, big, b"nonce", 0xffffff).unwrap();
+        assert_eq!(
+            keystream.take(16).collect::<Vec<u8>>(),
+            b"nonce\xff\xff\xffnonce\x00\x00\x00".to_vec()
+        );
+    }
+
+    // Counts the blocks it encrypts
+    struct CountingCipher {
+        inner: XorCipher,
+        blocks: Cell<usize>,
+    }
+
+    impl BlockCipher for CountingCipher {
+        fn block_size(&self) -> usize {
+            self.inner.block_size()
+        }
+
+        fn encrypt_block(&self, block: &mut [u8]) {
+            self.blocks.set(self.blocks.get() + 1);
+            self.inner.encrypt_block(block);
+        }
+
+        fn decrypt_block(&self, block: &mut [u8]) {
+            self.inner.decrypt_block(block);
+        }
+    }
+
+    #[test]
+    fn test_keystream_seek() {
+        let aes = aes_128(b"YELLOW SUBMARINE").unwrap();
+        let layout = CounterLayout::CRYPTOPALS;
+        let full: Vec<u8> = Keystream::new(&aes, layout, b"noncenon", 7)
+            .unwrap()
+            .take(100)
+            .collect();
+        let mut keystream = Keystream::new(&aes, layout, b"noncenon", 7).unwrap();
+        for &offset in [37, 0, 16, 99, 5].iter() {
+            keystream.seek(offset);
+            assert_eq!(keystream.next(), Some(full[offset]));
+        }
+        keystream.seek(30);
+        let rest: Vec<u8> = keystream.take(70).collect();
+        assert_eq!(rest, full[30..].to_vec());
+
+        let cipher = CountingCipher {
+            inner: XorCipher::new(&[0u8; 16]).unwrap(),
+            blocks: Cell::new(0),
+        };
+        let mut keystream = Keystream::new(&cipher, CounterLayout::NIST, &[], 0).unwrap();
+        keystream.seek(1_000_000 * 16 + 3);
+        assert_eq!(keystream.take(13).count(), 13);
+        assert_eq!(cipher.blocks.get(), 1);
+    }
+
+    #[test]
+    fn test_ctr_errors() {
+        let aes = aes_128(b"YELLOW SUBMARINE").unwrap();
+        let layout = CounterLayout {
+            nonce_len: 8,
+            counter_len: 4,
+            endianness: Endianness::Big,
+        };
+        assert_eq!(
+            ctr_apply(&aes, layout, &[0u8; 8], 0, b"data"),
+            Err(CtrError::InvalidLayout {
+                layout,
+                block_size: 16
+            })
+        );
+        assert_eq!(
+            ctr_apply(&aes, CounterLayout::GCM, &[0u8; 8], 0, b"data"),
+            Err(CtrError::InvalidNonceLength {
+                len: 8,
+                expected: 12
+            })
+        );
+        assert_eq!(
+            ctr_apply(&aes, CounterLayout::GCM, &[0u8; 12], 1 << 32, b"data"),
+            Err(CtrError::CounterTooLarge {
+                counter: 1 << 32,
+                counter_len: 4
+            })
+        );
+    }
+}
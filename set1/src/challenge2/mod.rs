@@ -16,6 +16,7 @@ If your function works properly, then when you feed it the string:
 
 */
 
+use crate::bits;
 use crate::challenge1::InvalidHexCharFoundError;
 use std::fmt;
 
@@ -70,6 +71,16 @@ pub fn fixed_xor(a: &str, b: &str) -> Result<String, XorError> {
     Ok(res)
 }
 
+// Same as fixed_xor on raw bytes
+pub fn fixed_xor_bytes(a: &[u8], b: &[u8]) -> Result<Vec<u8>, XorError> {
+    bits::xor(a, b).map_err(|err| {
+        XorError::DifferenceSizedError(DifferenceSize {
+            a: err.left,
+            b: err.right,
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +94,12 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), expected.to_string());
     }
+
+    #[test]
+    fn test_fixed_xor_bytes() {
+        let res = fixed_xor_bytes(b"\x1c\x01\x11", b"hit");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), b"the".to_vec());
+        assert!(fixed_xor_bytes(b"ab", b"abc").is_err());
+    }
 }
@@ -0,0 +1,191 @@
+/*
+Break "random access read/write" AES CTR
+
+Back to CTR. Encrypt the recovered plaintext from this file (the ECB exercise) under CTR with a
+random key (for this exercise the key should be unknown to you, but hold on to it).
+
+Now, write the code that allows you to "seek" into the ciphertext, decrypt, and re-encrypt with
+different plaintext. Expose this as a function, like, "edit(ciphertext, key, offset, newtext)".
+
+Imagine the "edit" function was exposed to attackers by means of an API call that didn't reveal the
+key or the original plaintext; the attacker has the ciphertext and controls the offset and "new
+text".
+
+Recover the original plaintext.
+
+Food for thought.
+A folkloric supposedly-secure way to encrypt data at rest... wait which it isn't.
+*/
+
+use crate::aes::InvalidKeyLengthError;
+use crate::block_cipher::BlockCipher;
+use crate::challenge18::{CounterLayout, CtrError, Keystream};
+use crate::challenge5::keystream_xor;
+use crate::challenge7::aes_128;
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EditError {
+    OffsetOutOfRange { offset: usize, len: usize },
+    Ctr(CtrError),
+    InvalidKey(InvalidKeyLengthError),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::OffsetOutOfRange { offset, len } => write!(
+                f,
+                "offset {} is past the end of the {} byte ciphertext",
+                offset, len
+            ),
+            EditError::Ctr(inner) => write!(f, "{}", inner),
+            EditError::InvalidKey(inner) => write!(f, "{}", inner),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+impl From<CtrError> for EditError {
+    fn from(err: CtrError) -> Self {
+        EditError::Ctr(err)
+    }
+}
+
+impl From<InvalidKeyLengthError> for EditError {
+    fn from(err: InvalidKeyLengthError) -> Self {
+        EditError::InvalidKey(err)
+    }
+}
+
+// Replace the plaintext from `offset` on with `newtext`, growing the ciphertext when it runs past
+// the end. Only the keystream under `newtext` is generated.
+pub fn ctr_edit<C: BlockCipher>(
+    cipher: &C,
+    layout: CounterLayout,
+    nonce: &[u8],
+    ciphertext: &[u8],
+    offset: usize,
+    newtext: &[u8],
+) -> Result<Vec<u8>, EditError> {
+    if offset > ciphertext.len() {
+        return Err(EditError::OffsetOutOfRange {
+            offset,
+            len: ciphertext.len(),
+        });
+    }
+    let mut keystream = Keystream::new(cipher, layout, nonce, 0)?;
+    keystream.seek(offset);
+    let mut edited = ciphertext[..offset].to_vec();
+    edited.extend(keystream_xor(newtext, keystream));
+    if let Some(rest) = ciphertext.get(offset + newtext.len()..) {
+        edited.extend_from_slice(rest);
+    }
+    Ok(edited)
+}
+
+// AES-128-CTR with the cryptopals layout and a zero nonce, as the challenge asks
+pub fn edit(
+    ciphertext: &[u8],
+    key: &[u8],
+    offset: usize,
+    newtext: &[u8],
+) -> Result<Vec<u8>, EditError> {
+    let nonce = [0u8; 8];
+    ctr_edit(
+        &aes_128(key)?,
+        CounterLayout::CRYPTOPALS,
+        &nonce,
+        ciphertext,
+        offset,
+        newtext,
+    )
+}
+
+// The edit API exposed to the attacker: it holds on to a random key and never reveals it
+pub struct EditOracle {
+    key: [u8; 16],
+}
+
+impl EditOracle {
+    pub fn new() -> Self {
+        EditOracle {
+            key: rand::thread_rng().gen(),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        edit(&[], &self.key, 0, plaintext).expect("16 byte key")
+    }
+
+    pub fn edit(
+        &self,
+        ciphertext: &[u8],
+        offset: usize,
+        newtext: &[u8],
+    ) -> Result<Vec<u8>, EditError> {
+        edit(ciphertext, &self.key, offset, newtext)
+    }
+}
+
+impl Default for EditOracle {
+    fn default() -> Self {
+        EditOracle::new()
+    }
+}
+
+// Editing the whole ciphertext to zeros hands back the keystream itself, and keystream XOR
+// ciphertext is the plaintext. `edit_oracle` is called with an offset and the new text.
+pub fn recover_plaintext<F>(ciphertext: &[u8], mut edit_oracle: F) -> Result<Vec<u8>, EditError>
+where
+    F: FnMut(&[u8], usize, &[u8]) -> Result<Vec<u8>, EditError>,
+{
+    let keystream = edit_oracle(ciphertext, 0, &vec![0u8; ciphertext.len()])?;
+    Ok(keystream_xor(ciphertext, keystream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenge7::lyrics;
+
+    #[test]
+    fn test_edit() {
+        let key = b"YELLOW SUBMARINE";
+        let ciphertext = edit(&[], key, 0, b"I'm back and I'm ringin' the bell").unwrap();
+        let res = edit(&ciphertext, key, 17, b"singin'");
+        assert!(res.is_ok());
+        let edited = res.unwrap();
+        assert_eq!(edited.len(), ciphertext.len());
+        assert_eq!(edited[..17], ciphertext[..17]);
+        assert_eq!(edited[24..], ciphertext[24..]);
+        let plaintext = edit(&edited, key, 0, &edited).unwrap();
+        // Editing with the ciphertext itself decrypts it
+        assert_eq!(plaintext, b"I'm back and I'm singin' the bell".to_vec());
+        let grown = edit(&ciphertext, key, 30, b"ll, ding dong").unwrap();
+        assert_eq!(grown.len(), 43);
+        assert_eq!(
+            edit(&ciphertext, key, 34, b"!"),
+            Err(EditError::OffsetOutOfRange {
+                offset: 34,
+                len: 33
+            })
+        );
+    }
+
+    #[test]
+    fn test_recover_plaintext() {
+        let plaintext = lyrics();
+        let oracle = EditOracle::new();
+        let ciphertext = oracle.encrypt(&plaintext);
+        assert_ne!(ciphertext, plaintext);
+        let res = recover_plaintext(&ciphertext, |c, offset, newtext| {
+            oracle.edit(c, offset, newtext)
+        });
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), plaintext);
+    }
+}
@@ -46,8 +46,68 @@ lazy_static! {
         map.insert(' ', 0.14);
         map
     };
+    static ref BIGRAM_FREQUENCY: HashMap<&'static str, f64> = {
+        let mut map = HashMap::new();
+        map.insert("th", 0.0356);
+        map.insert("he", 0.0307);
+        map.insert("in", 0.0243);
+        map.insert("er", 0.0205);
+        map.insert("an", 0.0199);
+        map.insert("re", 0.0185);
+        map.insert("on", 0.0176);
+        map.insert("at", 0.0149);
+        map.insert("en", 0.0145);
+        map.insert("nd", 0.0135);
+        map.insert("ti", 0.0134);
+        map.insert("es", 0.0134);
+        map.insert("or", 0.0128);
+        map.insert("te", 0.012);
+        map.insert("of", 0.0117);
+        map.insert("ed", 0.0117);
+        map.insert("is", 0.0113);
+        map.insert("it", 0.0112);
+        map.insert("al", 0.0109);
+        map.insert("ar", 0.0107);
+        map.insert("st", 0.0105);
+        map.insert("to", 0.0104);
+        map.insert("nt", 0.0104);
+        map.insert("ng", 0.0095);
+        map.insert("se", 0.0093);
+        map.insert("ha", 0.0093);
+        map.insert("as", 0.0087);
+        map.insert("ou", 0.0087);
+        map.insert("io", 0.0083);
+        map.insert("le", 0.0083);
+        map.insert("ve", 0.0083);
+        map.insert("co", 0.0079);
+        map.insert("me", 0.0079);
+        map.insert("de", 0.0076);
+        map.insert("hi", 0.0076);
+        map.insert("ri", 0.0073);
+        map.insert("ro", 0.0073);
+        map.insert("ic", 0.007);
+        map.insert("ne", 0.0069);
+        map.insert("ea", 0.0069);
+        map.insert("ra", 0.0069);
+        map.insert("ce", 0.0065);
+        map.insert("li", 0.0062);
+        map.insert("ch", 0.006);
+        map.insert("ll", 0.0058);
+        map.insert("be", 0.0058);
+        map.insert("ma", 0.0057);
+        map.insert("si", 0.0055);
+        map.insert("om", 0.0055);
+        map.insert("ur", 0.0054);
+        map
+    };
 }
 
+// Log-probabilities of bytes missing from LETTER_FREQUENCY
+const OTHER_PRINTABLE_LN: f64 = -6.0;
+const NON_PRINTABLE_LN: f64 = -14.0;
+// Frequency assumed for letter pairs missing from BIGRAM_FREQUENCY
+const UNLISTED_BIGRAM_FREQUENCY: f64 = 0.001;
+
 const HEX_ENCODED_STRING: &str =
     "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
 
@@ -83,7 +143,8 @@ fn decode_single_message_with_probability(byte: u8, s: &str) -> Option<MessageBu
     }
 }
 
-pub fn find_message_and_key(s: &str) -> MessageBundle {
+// Every single-char decryption of the hex encoded string, most probable first
+pub fn rank_messages_and_keys(s: &str) -> Vec<MessageBundle> {
     let mut possible_messages: Vec<MessageBundle> = vec![];
     for byte in 0..=255 {
         if let Some(message_bundle) = decode_single_message_with_probability(byte, s) {
@@ -91,7 +152,42 @@ pub fn find_message_and_key(s: &str) -> MessageBundle {
         }
     }
     possible_messages.sort_by(|a, b| a.probability.partial_cmp(&b.probability).unwrap());
-    possible_messages.iter().last().unwrap().clone()
+    possible_messages.reverse();
+    possible_messages
+}
+
+pub fn find_message_and_key(s: &str) -> MessageBundle {
+    rank_messages_and_keys(s).into_iter().next().unwrap()
+}
+
+fn ngram_char(byte: u8) -> char {
+    char::from(byte).to_ascii_lowercase()
+}
+
+// Log-likelihood of raw bytes being English text, using letter frequencies for single bytes plus a
+// bonus for every common pair of letters in BIGRAM_FREQUENCY. Higher is more English, useful to
+// compare candidate decryptions of the same ciphertext.
+pub fn ngram_score(text: &[u8]) -> f64 {
+    let unigrams = text.iter().fold(0.0, |acc, &byte| {
+        let log_probability = match LETTER_FREQUENCY.get(&ngram_char(byte)) {
+            Some(frequency) => frequency.ln(),
+            None if byte.is_ascii_graphic() || byte.is_ascii_whitespace() => OTHER_PRINTABLE_LN,
+            None => NON_PRINTABLE_LN,
+        };
+        acc + log_probability
+    });
+    let bigrams = text.windows(2).fold(0.0, |acc, pair| {
+        if !pair.iter().all(|b| b.is_ascii_alphabetic()) {
+            return acc;
+        }
+        let bigram: String = pair.iter().map(|&b| ngram_char(b)).collect();
+        acc + BIGRAM_FREQUENCY
+            .get(bigram.as_str())
+            .map_or(0.0, |frequency| {
+                (frequency / UNLISTED_BIGRAM_FREQUENCY).ln()
+            })
+    });
+    unigrams + bigrams
 }
 
 #[derive(Debug, Clone)]
@@ -118,4 +214,23 @@ mod tests {
             String::from("Cooking MC's like a pound of bacon")
         );
     }
+
+    #[test]
+    fn test_rank_messages_and_keys() {
+        let ranked = rank_messages_and_keys(HEX_ENCODED_STRING);
+        assert_eq!(ranked.len(), 256);
+        assert_eq!(ranked[0].key, 88);
+        assert!(ranked
+            .windows(2)
+            .all(|w| w[0].probability >= w[1].probability));
+    }
+
+    #[test]
+    fn test_ngram_score() {
+        let english = ngram_score(b"Cooking MC's like a pound of bacon");
+        let shuffled = ngram_score(b"nocab fo dnuop a ekil s'CM gnikooC");
+        let garbage = ngram_score(b"\x1b77316?x\x15\x1b\x7f+x413=x9x(7-6<x7>x:9;76");
+        assert!(english > shuffled);
+        assert!(shuffled > garbage);
+    }
 }
@@ -8,14 +8,74 @@ Find it.
 (Your code from #3 should help.)
 */
 
+use crate::challenge1::{hex_decode, hex_encode};
 use crate::challenge3::{find_message_and_key, MessageBundle};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+// Lines buffered per worker between the reader thread and the scoring threads
+const LINES_PER_WORKER: usize = 64;
+// Random lines decrypted to model the score of a line that isn't single-char XOR'd English
+const NULL_MODEL_SAMPLES: usize = 200;
+const NULL_MODEL_SEED: u64 = 4;
+const EULER_MASCHERONI: f64 = 0.577_215_664_901_532_9;
+
+#[derive(Debug, Clone)]
+pub struct LineCandidate {
+    pub line_number: usize,
+    pub key: u8,
+    pub message: String,
+    pub probability: f64,
+}
+
+impl PartialEq for LineCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for LineCandidate {}
+
+impl PartialOrd for LineCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Higher probability is better; on ties the earlier line wins
+impl Ord for LineCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.probability
+            .total_cmp(&other.probability)
+            .then_with(|| other.line_number.cmp(&self.line_number))
+    }
+}
+
+fn score_line(line_number: usize, line: &str) -> Option<LineCandidate> {
+    if line.is_empty() || !line.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let bundle = find_message_and_key(line);
+    Some(LineCandidate {
+        line_number,
+        key: bundle.key,
+        message: bundle.message,
+        probability: bundle.probability,
+    })
+}
 
 fn challenge4(filename: &str) -> String {
     let mut possible_lines: Vec<MessageBundle> = vec![];
     if let Ok(lines) = read_lines(filename) {
+        #[allow(clippy::manual_flatten)]
         for line in lines {
             if let Ok(lin) = line {
                 possible_lines.push(find_message_and_key(&lin));
@@ -26,6 +86,184 @@ fn challenge4(filename: &str) -> String {
     possible_lines.iter().last().unwrap().message.clone()
 }
 
+// Stream the hex encoded lines of `reader` through a pool of `workers` scoring threads, keeping
+// only the `top_k` best candidates. `on_found` is called every time a line enters the top `top_k`.
+// Lines that are empty, not UTF-8 or not hex are skipped. Returns the best candidates, best first.
+pub fn detect_single_char_xor<R, F>(
+    reader: R,
+    top_k: usize,
+    workers: usize,
+    mut on_found: F,
+) -> io::Result<Vec<LineCandidate>>
+where
+    R: BufRead + Send,
+    F: FnMut(&LineCandidate),
+{
+    let workers = workers.max(1);
+    let (line_tx, line_rx) = mpsc::sync_channel::<(usize, String)>(workers * LINES_PER_WORKER);
+    // Owned by the workers only, so once the last of them stops, even by panicking, the receiver is
+    // dropped and the reader's next send fails instead of blocking forever on a full channel
+    let line_rx = Arc::new(Mutex::new(line_rx));
+    let (candidate_tx, candidate_rx) = mpsc::channel::<LineCandidate>();
+    let mut best: BinaryHeap<Reverse<LineCandidate>> = BinaryHeap::with_capacity(top_k + 1);
+
+    thread::scope(|scope| {
+        let reader_thread = scope.spawn(move || -> io::Result<()> {
+            for numbered_line in hex_lines(reader) {
+                if line_tx.send(numbered_line?).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        for _ in 0..workers {
+            let line_rx = Arc::clone(&line_rx);
+            let candidate_tx = candidate_tx.clone();
+            scope.spawn(move || loop {
+                let next = line_rx.lock().unwrap().recv();
+                let (line_number, line) = match next {
+                    Ok(numbered_line) => numbered_line,
+                    Err(_) => break,
+                };
+                if let Some(candidate) = score_line(line_number, &line) {
+                    if candidate_tx.send(candidate).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(line_rx);
+        drop(candidate_tx);
+
+        for candidate in candidate_rx {
+            let worst_kept = best.peek().map(|Reverse(worst)| worst);
+            let keep = best.len() < top_k || worst_kept.is_some_and(|worst| candidate > *worst);
+            if !keep {
+                continue;
+            }
+            on_found(&candidate);
+            best.push(Reverse(candidate));
+            if best.len() > top_k {
+                best.pop();
+            }
+        }
+        reader_thread.join().unwrap()
+    })?;
+
+    let mut best: Vec<LineCandidate> = best.into_iter().map(|Reverse(c)| c).collect();
+    best.sort_by(|a, b| b.cmp(a));
+    Ok(best)
+}
+
+#[derive(Debug, Clone)]
+pub struct SignificantLine {
+    pub candidate: LineCandidate,
+    pub p_value: f64,
+}
+
+// Distribution of the best single-char decryption score of random lines of one length. The best
+// of 256 keys is a maximum, so it's fitted as a Gumbel distribution by the method of moments.
+// Random lines draw their bytes from `pool`, so noise that isn't uniform (e.g. mostly 7-bit)
+// doesn't look significant.
+#[derive(Debug, Clone, Copy)]
+struct NullModel {
+    location: f64,
+    scale: f64,
+}
+
+impl NullModel {
+    fn sample<R: Rng>(line_bytes: usize, pool: &[u8], rng: &mut R) -> NullModel {
+        let scores: Vec<f64> = (0..NULL_MODEL_SAMPLES)
+            .map(|_| {
+                let line: Vec<u8> = (0..line_bytes)
+                    .filter_map(|_| pool.choose(rng).cloned())
+                    .collect();
+                find_message_and_key(&hex_encode(&line)).probability
+            })
+            .collect();
+        let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+        let variance =
+            scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (scores.len() - 1) as f64;
+        let scale = variance.sqrt() * 6f64.sqrt() / std::f64::consts::PI;
+        NullModel {
+            location: mean - EULER_MASCHERONI * scale,
+            scale,
+        }
+    }
+
+    // Probability that a random line scores at least `score`
+    fn p_value(&self, score: f64) -> f64 {
+        if self.scale == 0.0 {
+            return if score > self.location { 0.0 } else { 1.0 };
+        }
+        let z = (score - self.location) / self.scale;
+        -(-(-z).exp()).exp_m1()
+    }
+}
+
+// Every hex line whose best single-char decryption scores significantly better than random lines
+// of the same length, most significant first. Random lines are drawn from the byte frequencies of
+// the whole input. `alpha` is the significance level for the whole input: each line is tested at
+// `alpha` divided by the number of lines (Bonferroni correction).
+pub fn significant_lines<R: BufRead>(reader: R, alpha: f64) -> io::Result<Vec<SignificantLine>> {
+    let mut candidates: Vec<LineCandidate> = vec![];
+    let mut pool: Vec<u8> = vec![];
+    for numbered_line in hex_lines(reader) {
+        let (line_number, line) = numbered_line?;
+        if let Some(candidate) = score_line(line_number, &line) {
+            pool.extend(hex_decode(&line).unwrap_or_default());
+            candidates.push(candidate);
+        }
+    }
+    let threshold = alpha / candidates.len().max(1) as f64;
+    let mut rng = StdRng::seed_from_u64(NULL_MODEL_SEED);
+    let mut null_models: HashMap<usize, NullModel> = HashMap::new();
+    let mut significant: Vec<SignificantLine> = vec![];
+    for candidate in candidates {
+        let line_bytes = candidate.message.chars().count();
+        let null_model = *null_models
+            .entry(line_bytes)
+            .or_insert_with(|| NullModel::sample(line_bytes, &pool, &mut rng));
+        let p_value = null_model.p_value(candidate.probability);
+        if p_value <= threshold {
+            significant.push(SignificantLine { candidate, p_value });
+        }
+    }
+    significant.sort_by(|a, b| {
+        a.p_value
+            .total_cmp(&b.p_value)
+            .then_with(|| b.candidate.cmp(&a.candidate))
+    });
+    Ok(significant)
+}
+
+// The most significant single-char XOR'd line, or None when no line stands out from random data
+pub fn most_significant_line<R: BufRead>(
+    reader: R,
+    alpha: f64,
+) -> io::Result<Option<SignificantLine>> {
+    Ok(significant_lines(reader, alpha)?.into_iter().next())
+}
+
+// Non empty hex lines of `reader`, trimmed and numbered from 1. Lines that aren't UTF-8 or aren't
+// hex are skipped instead of ending the scan, only read errors are passed on.
+pub fn hex_lines<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<(usize, String)>> {
+    reader
+        .split(b'\n')
+        .enumerate()
+        .filter_map(|(index, line)| match line {
+            Err(err) => Some(Err(err)),
+            Ok(bytes) => {
+                let line = String::from_utf8(bytes).ok()?;
+                let line = line.trim();
+                if line.is_empty() || !line.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return None;
+                }
+                Some(Ok((index + 1, line.to_string())))
+            }
+        })
+}
+
 pub fn read_lines<P: AsRef<Path>>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>> {
     let file = File::open(filename)?;
     Ok(io::BufReader::new(file).lines())
@@ -42,4 +280,105 @@ mod tests {
             String::from("Now that the party is jumping\n")
         );
     }
+
+    #[test]
+    fn test_detect_single_char_xor() {
+        let file = File::open("src/challenge4/input_challenge4.txt").unwrap();
+        let mut found = 0;
+        let res = detect_single_char_xor(io::BufReader::new(file), 3, 4, |_| found += 1);
+        assert!(res.is_ok());
+        let best = res.unwrap();
+        assert_eq!(best.len(), 3);
+        assert!(found >= 3);
+        assert_eq!(best[0].line_number, 171);
+        assert_eq!(best[0].key, 53);
+        assert_eq!(best[0].message, "Now that the party is jumping\n");
+        assert!(best
+            .windows(2)
+            .all(|w| w[0].probability >= w[1].probability));
+    }
+
+    fn random_hex_lines(lines: usize, seed: u64) -> Vec<String> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..lines)
+            .map(|_| hex_encode(&(0..30).map(|_| rng.gen()).collect::<Vec<u8>>()))
+            .collect()
+    }
+
+    #[test]
+    fn test_most_significant_line() {
+        let file = File::open("src/challenge4/input_challenge4.txt").unwrap();
+        let res = significant_lines(io::BufReader::new(file), 0.01);
+        assert!(res.is_ok());
+        let significant = res.unwrap();
+        assert_eq!(significant.len(), 1);
+        assert_eq!(significant[0].candidate.line_number, 171);
+        assert!(significant[0].p_value < 1e-5);
+    }
+
+    #[test]
+    fn test_most_significant_line_none_for_random_lines() {
+        let input = random_hex_lines(100, 1).join("\n");
+        let res = most_significant_line(input.as_bytes(), 0.01);
+        assert!(res.is_ok());
+        assert!(res.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_significant_lines_finds_several_encrypted_lines() {
+        let mut lines = random_hex_lines(100, 2);
+        let xor =
+            |text: &[u8], key: u8| hex_encode(&text.iter().map(|b| b ^ key).collect::<Vec<u8>>());
+        lines[17] = xor(b"Now that the party is jumping\n", 53);
+        lines[64] = xor(b"Cooking MC's like a pound of bacon", 88);
+        let res = significant_lines(lines.join("\n").as_bytes(), 0.01);
+        assert!(res.is_ok());
+        let mut found: Vec<usize> = res
+            .unwrap()
+            .iter()
+            .map(|s| s.candidate.line_number)
+            .collect();
+        found.sort_unstable();
+        assert_eq!(found, vec![18, 65]);
+    }
+
+    #[test]
+    fn test_hex_lines() {
+        let input = &b"4927\n\xff\xfe not utf-8\n\nnot hex\r\n0a0b\r\n"[..];
+        let lines: Vec<(usize, String)> = hex_lines(input).map(Result::unwrap).collect();
+        assert_eq!(
+            lines,
+            vec![(1, "4927".to_string()), (5, "0a0b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_detect_single_char_xor_skips_non_utf8_lines() {
+        let mut input = b"\xff\xfe\n".to_vec();
+        input.extend(std::fs::read("src/challenge4/input_challenge4.txt").unwrap());
+        let res = detect_single_char_xor(&input[..], 1, 2, |_| {});
+        assert!(res.is_ok());
+        let best = res.unwrap();
+        assert_eq!(best[0].line_number, 172);
+        assert_eq!(best[0].message, "Now that the party is jumping\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "stop the scan")]
+    fn test_detect_single_char_xor_passes_on_found_panic() {
+        // More lines than the channel between the reader and the workers holds
+        let input = std::fs::read("src/challenge4/input_challenge4.txt")
+            .unwrap()
+            .repeat(3);
+        assert!(input.split(|&b| b == b'\n').count() > 2 * LINES_PER_WORKER);
+        let _ = detect_single_char_xor(&input[..], 1, 2, |_| panic!("stop the scan"));
+    }
+
+    #[test]
+    fn test_detect_single_char_xor_skips_empty_and_invalid_lines() {
+        let input = "\nnot hex at all\n".as_bytes();
+        let res = detect_single_char_xor(input, 5, 2, |_| panic!("nothing to find"));
+        assert!(res.is_ok());
+        assert!(res.unwrap().is_empty());
+    }
 }
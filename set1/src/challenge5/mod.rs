@@ -19,14 +19,22 @@ Encrypt a bunch of stuff using your repeating-key XOR function. Encrypt your mai
 password file. Your .sig file. Get a feel for it. I promise, we aren't wasting your time with this.
 */
 
+use crate::challenge1::hex_encode;
+
 pub fn repeating_xor(text: &str, key: &str) -> String {
-    let mut result = String::new();
-    let mut key_iter = key.bytes().cycle();
-    for text_byte in text.bytes() {
-        let xored_byte = text_byte ^ key_iter.next().unwrap();
-        result.push_str(format!("{:0>2x}", xored_byte).as_str());
-    }
-    result
+    hex_encode(&repeating_xor_bytes(text.as_bytes(), key.as_bytes()))
+}
+
+pub fn repeating_xor_bytes(text: &[u8], key: &[u8]) -> Vec<u8> {
+    keystream_xor(text, key.iter().copied().cycle())
+}
+
+// XOR every byte of text with the next byte of the keystream, stopping at whichever ends first
+pub fn keystream_xor<K: IntoIterator<Item = u8>>(text: &[u8], keystream: K) -> Vec<u8> {
+    text.iter()
+        .zip(keystream)
+        .map(|(text_byte, key_byte)| text_byte ^ key_byte)
+        .collect()
 }
 
 #[cfg(test)]
@@ -39,4 +47,11 @@ mod tests {
         let expected = "0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f";
         assert_eq!(expected.to_string(), repeating_xor(text, "ICE"));
     }
+
+    #[test]
+    fn test_keystream_xor() {
+        assert_eq!(keystream_xor(b"abc", vec![1, 2, 3, 4]), b"```".to_vec());
+        assert_eq!(keystream_xor(b"abc", vec![0]), b"a".to_vec());
+        assert_eq!(keystream_xor(b"", std::iter::repeat(7)), vec![]);
+    }
 }
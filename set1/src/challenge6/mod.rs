@@ -54,11 +54,17 @@ aren't any blatant errors in this text. In particular: the "wokka wokka!!!" edit
 37.
 */
 
-use super::challenge1::InvalidHexCharFoundError;
-use super::challenge4::read_lines;
-use super::challenge5::repeating_xor;
+use super::bits::{self, LengthMismatchError};
+use super::blocks::transpose;
+use super::challenge1::{hex_decode, hex_encode, InvalidHexCharFoundError};
+use super::challenge3::{find_message_and_key, ngram_score, rank_messages_and_keys};
+use super::challenge5::repeating_xor_bytes;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead};
+use std::ops::RangeInclusive;
 use std::path::Path;
 
 fn base64_char_to_binary(input: &str) -> Result<String, InvalidHexCharFoundError> {
@@ -70,7 +76,7 @@ fn base64_char_to_binary(input: &str) -> Result<String, InvalidHexCharFoundError
             b'0'..=b'9' => Ok(format!("{:06b}", byte - b'0' + 52)),
             b'+' => Ok(format!("{:06b}", 62)),
             b'/' => Ok(format!("{:06b}", 63)),
-            b'=' => Ok(format!("")),
+            b'=' => Ok(String::new()),
             _ => Err(InvalidHexCharFoundError {
                 ch: char::from(byte),
             }),
@@ -87,19 +93,14 @@ fn add_char(s: &mut String, slice: &str) {
             10..=15 => s.push_str(&format!("{:x}", value)),
             _ => panic!(
                 "This shouldn't happen: value: {}, string so far: {}",
-                value, &s
+                value, s
             ),
         }
     }
 }
 
-fn hamming_distance(s: &str, t: &str) -> usize {
-    s.bytes().zip(t.bytes()).fold(0, |acc, pair| {
-        acc + format!("{:b}", pair.0 ^ pair.1)
-            .chars()
-            .filter(|&c| c == '1')
-            .count()
-    })
+fn hamming_distance(s: &str, t: &str) -> Result<u32, LengthMismatchError> {
+    bits::hamming_distance(s.as_bytes(), t.as_bytes())
 }
 
 fn base64_decode(input: &str) -> Result<String, InvalidHexCharFoundError> {
@@ -113,45 +114,174 @@ fn base64_decode(input: &str) -> Result<String, InvalidHexCharFoundError> {
     Ok(result)
 }
 
-#[derive(Debug)]
-struct KeysizeAverageDistance {
-    keysize: usize,
-    avg_distance: f64,
+// Decode base64 into raw bytes, ignoring whitespace such as line breaks
+pub fn base64_decode_bytes(input: &str) -> Result<Vec<u8>, InvalidHexCharFoundError> {
+    let compact: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    hex_decode(&base64_decode(&compact)?)
 }
 
-fn read_file<P: AsRef<Path>>(filename: P) -> io::Result<io::BufReader<File>> {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file))
+// Probability that two bytes picked at random from the input are equal
+pub fn index_of_coincidence(bytes: &[u8]) -> f64 {
+    if bytes.len() < 2 {
+        return 0.0;
+    }
+    let mut counts: HashMap<u8, usize> = HashMap::new();
+    for byte in bytes {
+        *counts.entry(*byte).or_insert(0) += 1;
+    }
+    let pairs = counts.values().map(|&n| n * (n - 1)).sum::<usize>();
+    pairs as f64 / (bytes.len() * (bytes.len() - 1)) as f64
 }
 
-fn find_keysize(file: &str) -> usize {
-    let mut avg_distances: Vec<KeysizeAverageDistance> = vec![];
-    let mut all_lines: Vec<u8> = Vec::new();
-    if let Ok(mut reader) = read_file(file) {
-        if reader.read_until(b'\0', &mut all_lines).is_ok() {
-            let all_lines_res = String::from_utf8(all_lines);
-            if let Ok(all_lines_str) = all_lines_res {
-                for keysize in 2..=40 {
-                    let mut distances: Vec<f64> = Vec::new();
-                    for i in (0..all_lines_str.len()).step_by(keysize * 2) {
-                        if let Some(s) = all_lines_str.get(i..i + keysize) {
-                            if let Some(t) = all_lines_str.get(i + keysize..i + keysize * 2) {
-                                distances.push(hamming_distance(s, t) as f64 / keysize as f64);
-                            }
-                        }
-                    }
-                    let sum_distances = distances.iter().sum::<f64>();
-                    let avg_distance = sum_distances / distances.len() as f64;
-                    avg_distances.push(KeysizeAverageDistance {
-                        keysize,
-                        avg_distance,
-                    });
-                }
-            }
+// Blocks compared pairwise by the hamming distance estimator
+const HAMMING_BLOCKS: usize = 24;
+// Length of the repeated sequences whose spacing the Kasiski estimator measures
+const KASISKI_SEQUENCE_LEN: usize = 3;
+// A keysize ranks ahead of its multiples when its score is at most this far behind theirs
+const MULTIPLE_TOLERANCE: f64 = 0.05;
+
+// Scores of every keysize estimator for one keysize. `score` combines the estimators after
+// scaling each of them to [0, 1] over all the keysizes tried, higher is more likely.
+#[derive(Debug, Clone)]
+pub struct KeysizeAverageDistance {
+    pub keysize: usize,
+    // Normalized hamming distance averaged over all pairs of the first blocks, lower is better
+    pub avg_distance: f64,
+    // Index of coincidence averaged over the key columns, higher is better
+    pub column_ioc: f64,
+    // How much more often than by chance repeated sequences are a multiple of keysize apart
+    pub kasiski: f64,
+    // Fraction of bytes equal to the byte keysize positions later, higher is better
+    pub autocorrelation: f64,
+    pub score: f64,
+}
+
+fn all_pairs_distance(bytes: &[u8], keysize: usize) -> Option<f64> {
+    let blocks: Vec<&[u8]> = bytes.chunks_exact(keysize).take(HAMMING_BLOCKS).collect();
+    if blocks.len() < 2 {
+        return None;
+    }
+    let mut distances: Vec<f64> = vec![];
+    for (i, s) in blocks.iter().enumerate() {
+        for t in blocks.iter().skip(i + 1) {
+            let distance = bits::hamming_distance(s, t).unwrap();
+            distances.push(distance as f64 / keysize as f64);
         }
     }
-    avg_distances.sort_by(|a, b| a.avg_distance.partial_cmp(&b.avg_distance).unwrap());
-    avg_distances.get(0).unwrap().keysize
+    Some(distances.iter().sum::<f64>() / distances.len() as f64)
+}
+
+fn column_ioc(bytes: &[u8], keysize: usize) -> f64 {
+    let columns = transpose(bytes, keysize);
+    columns.iter().map(|c| index_of_coincidence(c)).sum::<f64>() / keysize as f64
+}
+
+// Distances between consecutive occurrences of every repeated sequence
+fn repeated_sequence_spacings(bytes: &[u8]) -> Vec<usize> {
+    let mut last_seen: HashMap<&[u8], usize> = HashMap::new();
+    let mut spacings: Vec<usize> = vec![];
+    for (position, sequence) in bytes.windows(KASISKI_SEQUENCE_LEN).enumerate() {
+        if let Some(previous) = last_seen.insert(sequence, position) {
+            spacings.push(position - previous);
+        }
+    }
+    spacings
+}
+
+fn kasiski(spacings: &[usize], keysize: usize) -> f64 {
+    if spacings.is_empty() {
+        return 0.0;
+    }
+    let divisible = spacings.iter().filter(|&&s| s % keysize == 0).count();
+    divisible as f64 / spacings.len() as f64 * keysize as f64
+}
+
+fn autocorrelation(bytes: &[u8], keysize: usize) -> f64 {
+    let shifted = bytes.iter().skip(keysize);
+    let matches = bytes.iter().zip(shifted).filter(|(a, b)| a == b).count();
+    matches as f64 / (bytes.len() - keysize) as f64
+}
+
+// Scale values to [0, 1], all zeros when they are all equal
+fn min_max_scale(values: &[f64]) -> Vec<f64> {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|v| {
+            if max > min {
+                (v - min) / (max - min)
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+// Rank the keysizes by combining the hamming distance, column index of coincidence, Kasiski and
+// autocorrelation estimators, most likely first. Estimators agree on multiples of the keysize, so
+// a keysize ranks ahead of any multiple that scores at most MULTIPLE_TOLERANCE better. Keysizes
+// for which the input doesn't hold two blocks are left out.
+pub fn find_keysize(bytes: &[u8], keysizes: RangeInclusive<usize>) -> Vec<KeysizeAverageDistance> {
+    let spacings = repeated_sequence_spacings(bytes);
+    let mut estimates: Vec<KeysizeAverageDistance> = vec![];
+    for keysize in keysizes.filter(|&k| k > 0) {
+        let avg_distance = match all_pairs_distance(bytes, keysize) {
+            Some(avg_distance) => avg_distance,
+            None => continue,
+        };
+        estimates.push(KeysizeAverageDistance {
+            keysize,
+            avg_distance,
+            column_ioc: column_ioc(bytes, keysize),
+            kasiski: kasiski(&spacings, keysize),
+            autocorrelation: autocorrelation(bytes, keysize),
+            score: 0.0,
+        });
+    }
+    let estimators: Vec<Vec<f64>> = vec![
+        min_max_scale(
+            &estimates
+                .iter()
+                .map(|e| -e.avg_distance)
+                .collect::<Vec<f64>>(),
+        ),
+        min_max_scale(&estimates.iter().map(|e| e.column_ioc).collect::<Vec<f64>>()),
+        min_max_scale(&estimates.iter().map(|e| e.kasiski).collect::<Vec<f64>>()),
+        min_max_scale(
+            &estimates
+                .iter()
+                .map(|e| e.autocorrelation)
+                .collect::<Vec<f64>>(),
+        ),
+    ];
+    for (i, estimate) in estimates.iter_mut().enumerate() {
+        estimate.score = estimators.iter().map(|e| e[i]).sum::<f64>() / estimators.len() as f64;
+    }
+
+    let rank_scores: HashMap<usize, f64> = estimates
+        .iter()
+        .map(|estimate| {
+            let best_multiple = estimates
+                .iter()
+                .filter(|other| other.keysize % estimate.keysize == 0)
+                .filter(|other| other.score - estimate.score <= MULTIPLE_TOLERANCE)
+                .map(|other| other.score)
+                .fold(estimate.score, f64::max);
+            (estimate.keysize, best_multiple)
+        })
+        .collect();
+    estimates.sort_by(|a, b| {
+        rank_scores[&b.keysize]
+            .total_cmp(&rank_scores[&a.keysize])
+            .then_with(|| a.keysize.cmp(&b.keysize))
+    });
+    estimates
+}
+
+fn read_file<P: AsRef<Path>>(filename: P) -> io::Result<io::BufReader<File>> {
+    let file = File::open(filename)?;
+    Ok(io::BufReader::new(file))
 }
 
 fn break_in_keysize_blocks(keysize: usize, file: &str) -> Vec<String> {
@@ -174,7 +304,7 @@ fn break_in_keysize_blocks(keysize: usize, file: &str) -> Vec<String> {
 
 fn transpose_bytes_of_blocks(blocks: Vec<String>) -> Vec<String> {
     let mut res: Vec<String> = Vec::new();
-    if let Some(first) = blocks.get(0) {
+    if let Some(first) = blocks.first() {
         for n in 0..first.len() {
             for i in 0..blocks.len() {
                 if let Some(slice) = blocks.get(i).unwrap().get(n..n + 1) {
@@ -192,9 +322,154 @@ fn transpose_bytes_of_blocks(blocks: Vec<String>) -> Vec<String> {
     res
 }
 
+#[derive(Debug)]
+pub enum BreakXorError {
+    CiphertextTooShort(usize),
+}
+
+impl fmt::Display for BreakXorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BreakXorError::CiphertextTooShort(len) => {
+                write!(
+                    f,
+                    "ciphertext of {} bytes is too short to guess a keysize",
+                    len
+                )
+            }
+        }
+    }
+}
+
+impl Error for BreakXorError {}
+
+// Recover the key of a repeating-key XOR encrypted ciphertext, returns the key and the plaintext
+pub fn break_repeating_key_xor(ciphertext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), BreakXorError> {
+    let keysize = find_keysize(ciphertext, 2..=40)
+        .first()
+        .ok_or(BreakXorError::CiphertextTooShort(ciphertext.len()))?
+        .keysize;
+    let key: Vec<u8> = transpose(ciphertext, keysize)
+        .iter()
+        .map(|column| find_message_and_key(&hex_encode(column)).key)
+        .collect();
+    let plaintext = repeating_xor_bytes(ciphertext, &key);
+    Ok((key, plaintext))
+}
+
+// Improve a recovered repeating-key XOR key by hill-climbing: swap in each of the `top_k` best
+// single-char candidates of every key column and keep any change that raises the n-gram score of
+// the whole plaintext, until no change does
+pub fn refine_key(ciphertext: &[u8], key: &[u8], top_k: usize) -> Vec<u8> {
+    let mut best_key = key.to_vec();
+    if key.is_empty() {
+        return best_key;
+    }
+    let candidates: Vec<Vec<u8>> = transpose(ciphertext, key.len())
+        .iter()
+        .map(|column| {
+            rank_messages_and_keys(&hex_encode(column))
+                .iter()
+                .take(top_k)
+                .map(|bundle| bundle.key)
+                .collect()
+        })
+        .collect();
+    let mut best_score = ngram_score(&repeating_xor_bytes(ciphertext, &best_key));
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for (position, column_candidates) in candidates.iter().enumerate() {
+            for &candidate in column_candidates {
+                if candidate == best_key[position] {
+                    continue;
+                }
+                let mut key = best_key.clone();
+                key[position] = candidate;
+                let score = ngram_score(&repeating_xor_bytes(ciphertext, &key));
+                if score > best_score {
+                    best_key = key;
+                    best_score = score;
+                    improved = true;
+                }
+            }
+        }
+    }
+    best_key
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyCandidate {
+    pub key: Vec<u8>,
+    pub score: f64,
+}
+
+// N-gram score of the plaintext bytes decrypted by a key prefix: the first key.len() bytes of every
+// block of keysize bytes
+fn key_prefix_score(ciphertext: &[u8], keysize: usize, key: &[u8]) -> f64 {
+    ciphertext
+        .chunks(keysize)
+        .map(|block| {
+            ngram_score(&repeating_xor_bytes(
+                &block[..key.len().min(block.len())],
+                key,
+            ))
+        })
+        .sum()
+}
+
+// Enumerate repeating-key XOR keys by beam search: extend every kept key prefix with each of the
+// `top_k` best single-char candidates of the next key column and keep the `beam_width` prefixes
+// whose plaintext scores best. Returns the `best_n` complete keys ranked by the n-gram score of the
+// whole plaintext, best first.
+pub fn beam_search_keys(
+    ciphertext: &[u8],
+    keysize: usize,
+    top_k: usize,
+    beam_width: usize,
+    best_n: usize,
+) -> Vec<KeyCandidate> {
+    let candidates: Vec<Vec<u8>> = transpose(ciphertext, keysize)
+        .iter()
+        .map(|column| {
+            rank_messages_and_keys(&hex_encode(column))
+                .iter()
+                .take(top_k)
+                .map(|bundle| bundle.key)
+                .collect()
+        })
+        .collect();
+    let mut beam: Vec<KeyCandidate> = vec![KeyCandidate {
+        key: vec![],
+        score: 0.0,
+    }];
+    for column_candidates in &candidates {
+        let mut extended: Vec<KeyCandidate> = vec![];
+        for prefix in &beam {
+            for &candidate in column_candidates {
+                let mut key = prefix.key.clone();
+                key.push(candidate);
+                let score = key_prefix_score(ciphertext, keysize, &key);
+                extended.push(KeyCandidate { key, score });
+            }
+        }
+        extended.sort_by(|a, b| b.score.total_cmp(&a.score));
+        extended.truncate(beam_width);
+        beam = extended;
+    }
+    for candidate in beam.iter_mut() {
+        candidate.score = ngram_score(&repeating_xor_bytes(ciphertext, &candidate.key));
+    }
+    beam.sort_by(|a, b| b.score.total_cmp(&a.score));
+    beam.truncate(best_n);
+    beam
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::loader::{load, Format, Source};
+    use std::path::PathBuf;
 
     #[test]
     fn test_base64_decode() {
@@ -205,18 +480,54 @@ mod tests {
         assert_eq!(res.unwrap(), expected_output)
     }
 
+    #[test]
+    fn test_base64_decode_bytes() {
+        let res = base64_decode_bytes("SSdt\nIGtp\nbGxp\nbmc=");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), b"I'm killing".to_vec());
+    }
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert_eq!(index_of_coincidence(b"aaaa"), 1.0);
+        assert_eq!(index_of_coincidence(b"abcd"), 0.0);
+        assert_eq!(index_of_coincidence(b"aabb"), 2.0 * 2.0 / 12.0);
+    }
+
     #[test]
     fn test_hamming_distance() {
         let s = "this is a test";
         let t = "wokka wokka!!!";
-        let expected_output: usize = 37;
-        assert_eq!(hamming_distance(s, t), expected_output);
+        let expected_output: u32 = 37;
+        assert_eq!(hamming_distance(s, t), Ok(expected_output));
+        assert!(hamming_distance(s, "wokka").is_err());
+    }
+
+    fn challenge6_ciphertext() -> Vec<u8> {
+        let file = Source::File(PathBuf::from("src/challenge6/6.txt"));
+        load(&file, Format::Base64Blob).unwrap().into_blob()
     }
 
     #[test]
     fn test_find_keysize() {
-        let input = "src/challenge6/6.txt";
-        assert_eq!(find_keysize(input), 20);
+        let ranked = find_keysize(&challenge6_ciphertext(), 2..=40);
+        assert_eq!(ranked.len(), 39);
+        assert_eq!(ranked[0].keysize, 29);
+    }
+
+    #[test]
+    fn test_find_keysize_prefers_key_over_its_multiples() {
+        let ciphertext = challenge6_ciphertext();
+        let plaintext = repeating_xor_bytes(&ciphertext, b"Terminator X: Bring the noise");
+        let ranked = find_keysize(&repeating_xor_bytes(&plaintext, b"ICE"), 2..=20);
+        assert_eq!(ranked.len(), 19);
+        assert_eq!(ranked[0].keysize, 3);
+    }
+
+    #[test]
+    fn test_find_keysize_short_input() {
+        assert!(find_keysize(b"abc", 2..=40).is_empty());
+        assert_eq!(find_keysize(b"abcdefgh", 2..=40).len(), 3);
     }
 
     #[test]
@@ -228,6 +539,54 @@ mod tests {
             .all(|x| x.len() == 20));
     }
 
+    #[test]
+    fn test_break_repeating_key_xor() {
+        let res = break_repeating_key_xor(&challenge6_ciphertext());
+        assert!(res.is_ok());
+        let (key, plaintext) = res.unwrap();
+        assert_eq!(key, b"Terminator X: Bring the noise".to_vec());
+        assert!(plaintext.starts_with(b"I'm back and I'm ringin' the bell"));
+    }
+
+    #[test]
+    fn test_refine_key() {
+        let ciphertext = challenge6_ciphertext();
+        let key = b"Terminator X: Bring the noise".to_vec();
+        assert_eq!(refine_key(&ciphertext, &key, 5), key);
+
+        let columns = transpose(&ciphertext, key.len());
+        let mut wrong_key = key.clone();
+        wrong_key[3] = rank_messages_and_keys(&hex_encode(&columns[3]))[1].key;
+        wrong_key[11] = rank_messages_and_keys(&hex_encode(&columns[11]))[2].key;
+        assert_ne!(wrong_key, key);
+        assert_eq!(refine_key(&ciphertext, &wrong_key, 5), key);
+    }
+
+    #[test]
+    fn test_beam_search_keys() {
+        let text = b"Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal";
+        let ciphertext = repeating_xor_bytes(text, b"ICE");
+        let keys = beam_search_keys(&ciphertext, 3, 4, 8, 5);
+        assert_eq!(keys.len(), 5);
+        assert_eq!(keys[0].key, b"ICE".to_vec());
+        assert!(keys.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+
+    #[test]
+    fn test_beam_search_keys_challenge6() {
+        let keys = beam_search_keys(&challenge6_ciphertext(), 29, 2, 3, 2);
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].key, b"Terminator X: Bring the noise".to_vec());
+    }
+
+    #[test]
+    fn test_break_repeating_key_xor_too_short() {
+        assert!(matches!(
+            break_repeating_key_xor(b"abc"),
+            Err(BreakXorError::CiphertextTooShort(3))
+        ));
+    }
+
     #[test]
     fn test_transpose_bytes_of_blocks() {
         let input = vec![format!("hello"), format!("cruel"), format!("world")];
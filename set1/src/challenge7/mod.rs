@@ -0,0 +1,252 @@
+/*
+AES in ECB mode
+
+The Base64-encoded content in this file has been encrypted via AES-128 in ECB mode under the key
+
+"YELLOW SUBMARINE".
+
+(case-sensitive, without the quotes; exactly 16 characters; I like "YELLOW SUBMARINE" because it's
+exactly 16 bytes long, and now you do too).
+
+Decrypt it. You know the key, after all.
+
+Easiest way: use OpenSSL::Cipher and give it AES-128-ECB as the cipher.
+
+Do this with code.
+You can obviously decrypt this using the OpenSSL command-line tool, but we're having you get ECB
+working in code for a reason. You'll need it a lot later on, and not just for attacking ECB.
+*/
+
+use crate::aes::{Aes, InvalidKeyLengthError, KeySize};
+use crate::block_cipher::BlockCipher;
+use crate::challenge6::base64_decode_bytes;
+use crate::challenge9::{Padding, PaddingError, Pkcs7};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum EcbError {
+    InvalidLength { len: usize, block_size: usize },
+    Padding(PaddingError),
+    InvalidKey(InvalidKeyLengthError),
+    InvalidBase64Char(char),
+}
+
+impl fmt::Display for EcbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EcbError::InvalidLength { len, block_size } => write!(
+                f,
+                "length {} is not a multiple of the block size {}",
+                len, block_size
+            ),
+            EcbError::Padding(inner) => write!(f, "{}", inner),
+            EcbError::InvalidKey(inner) => write!(f, "{}", inner),
+            EcbError::InvalidBase64Char(ch) => write!(f, "invalid base64 char {:?}", ch),
+        }
+    }
+}
+
+impl Error for EcbError {}
+
+// Lengths that aren't a whole number of blocks are reported the same way whatever the padding
+impl From<PaddingError> for EcbError {
+    fn from(err: PaddingError) -> Self {
+        match err {
+            PaddingError::InvalidLength { len, block_size } => {
+                EcbError::InvalidLength { len, block_size }
+            }
+            _ => EcbError::Padding(err),
+        }
+    }
+}
+
+impl From<InvalidKeyLengthError> for EcbError {
+    fn from(err: InvalidKeyLengthError) -> Self {
+        EcbError::InvalidKey(err)
+    }
+}
+
+fn check_length(len: usize, block_size: usize) -> Result<(), EcbError> {
+    if !len.is_multiple_of(block_size) {
+        return Err(EcbError::InvalidLength { len, block_size });
+    }
+    Ok(())
+}
+
+pub fn ecb_encrypt<C: BlockCipher, P: Padding + ?Sized>(
+    cipher: &C,
+    plaintext: &[u8],
+    padding: &P,
+) -> Result<Vec<u8>, EcbError> {
+    let block_size = cipher.block_size();
+    let mut data = padding.pad(plaintext, block_size)?;
+    check_length(data.len(), block_size)?;
+    for block in data.chunks_mut(block_size) {
+        cipher.encrypt_block(block);
+    }
+    Ok(data)
+}
+
+pub fn ecb_decrypt<C: BlockCipher, P: Padding + ?Sized>(
+    cipher: &C,
+    ciphertext: &[u8],
+    padding: &P,
+) -> Result<Vec<u8>, EcbError> {
+    let block_size = cipher.block_size();
+    check_length(ciphertext.len(), block_size)?;
+    let mut data = ciphertext.to_vec();
+    for block in data.chunks_mut(block_size) {
+        cipher.decrypt_block(block);
+    }
+    let len = padding.unpad(&data, block_size)?.len();
+    data.truncate(len);
+    Ok(data)
+}
+
+// AES with a key that must be 16 bytes long
+pub fn aes_128(key: &[u8]) -> Result<Aes, InvalidKeyLengthError> {
+    if KeySize::from_key_len(key.len())? != KeySize::Aes128 {
+        return Err(InvalidKeyLengthError { len: key.len() });
+    }
+    Aes::new(key)
+}
+
+pub fn aes_128_ecb_encrypt<P: Padding + ?Sized>(
+    key: &[u8],
+    plaintext: &[u8],
+    padding: &P,
+) -> Result<Vec<u8>, EcbError> {
+    ecb_encrypt(&aes_128(key)?, plaintext, padding)
+}
+
+pub fn aes_128_ecb_decrypt<P: Padding + ?Sized>(
+    key: &[u8],
+    ciphertext: &[u8],
+    padding: &P,
+) -> Result<Vec<u8>, EcbError> {
+    ecb_decrypt(&aes_128(key)?, ciphertext, padding)
+}
+
+// Decrypt base64 encoded AES-128-ECB ciphertext with PKCS#7 padding, as given in the challenge
+pub fn decrypt_base64(encoded: &str, key: &[u8]) -> Result<Vec<u8>, EcbError> {
+    let ciphertext =
+        base64_decode_bytes(encoded).map_err(|err| EcbError::InvalidBase64Char(err.ch))?;
+    aes_128_ecb_decrypt(key, &ciphertext, &Pkcs7)
+}
+
+// Plaintext of the challenge file, for the tests of later challenges that encrypt it again
+#[cfg(test)]
+pub fn lyrics() -> Vec<u8> {
+    let encoded = std::fs::read_to_string("src/challenge7/7.txt").unwrap();
+    decrypt_base64(&encoded, b"YELLOW SUBMARINE").unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_cipher::XorCipher;
+    use crate::challenge1::{base64_encode, hex_decode, hex_encode};
+    use crate::challenge9::{AnsiX923, NoPadding};
+    use std::fs;
+    use std::path::Path;
+
+    const NIST_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const NIST_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                                  30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_aes_128_ecb_known_answer() {
+        let key = hex_decode(NIST_KEY).unwrap();
+        let plaintext = hex_decode(NIST_PLAINTEXT).unwrap();
+        let res = aes_128_ecb_encrypt(&key, &plaintext, &NoPadding);
+        assert!(res.is_ok());
+        let ciphertext = res.unwrap();
+        assert_eq!(
+            hex_encode(&ciphertext),
+            "3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf\
+             43b1cd7f598ece23881b00e3ed0306887b0c785e27e8ad3f8223207104725dd4"
+        );
+        let res = aes_128_ecb_decrypt(&key, &ciphertext, &NoPadding);
+        assert_eq!(res.unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_ecb_padding() {
+        let cipher = XorCipher::new(&[0u8; 4]).unwrap();
+        let res = ecb_encrypt(&cipher, b"abcdef", &Pkcs7);
+        assert_eq!(res.unwrap(), b"abcdef\x02\x02".to_vec());
+        let res = ecb_encrypt(&cipher, b"abcd", &Pkcs7);
+        assert_eq!(res.unwrap(), b"abcd\x04\x04\x04\x04".to_vec());
+        let res = ecb_decrypt(&cipher, b"abcdef\x02\x02", &Pkcs7);
+        assert_eq!(res.unwrap(), b"abcdef".to_vec());
+        let res = ecb_decrypt(&cipher, b"abcdef\x02\x02", &NoPadding);
+        assert_eq!(res.unwrap(), b"abcdef\x02\x02".to_vec());
+        let res = ecb_decrypt(&cipher, b"abcdef\x01\x02", &Pkcs7);
+        assert!(matches!(
+            res,
+            Err(EcbError::Padding(PaddingError::InconsistentPadding))
+        ));
+        let res = ecb_decrypt(&cipher, b"abcdefg\x05", &Pkcs7);
+        assert!(matches!(
+            res,
+            Err(EcbError::Padding(PaddingError::PaddingTooLong { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_ecb_other_padding() {
+        let cipher = XorCipher::new(&[0u8; 4]).unwrap();
+        let res = ecb_encrypt(&cipher, b"abcdef", &AnsiX923);
+        assert_eq!(res.unwrap(), b"abcdef\x00\x02".to_vec());
+        let res = ecb_decrypt(&cipher, b"abcdef\x00\x02", &AnsiX923);
+        assert_eq!(res.unwrap(), b"abcdef".to_vec());
+    }
+
+    #[test]
+    fn test_ecb_errors() {
+        let cipher = XorCipher::new(b"key!").unwrap();
+        assert!(matches!(
+            ecb_encrypt(&cipher, b"abcdef", &NoPadding),
+            Err(EcbError::InvalidLength {
+                len: 6,
+                block_size: 4
+            })
+        ));
+        assert!(matches!(
+            ecb_decrypt(&cipher, b"abcdefg", &Pkcs7),
+            Err(EcbError::InvalidLength {
+                len: 7,
+                block_size: 4
+            })
+        ));
+        assert!(matches!(
+            aes_128_ecb_decrypt(&[0u8; 24], &[0u8; 16], &NoPadding),
+            Err(EcbError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            decrypt_base64("SSdt*", b"YELLOW SUBMARINE"),
+            Err(EcbError::InvalidBase64Char('*'))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_base64_round_trip() {
+        let key = b"YELLOW SUBMARINE";
+        let plaintext = b"I'm back and I'm ringin' the bell \nA rockin' on the mike";
+        let ciphertext = aes_128_ecb_encrypt(key, plaintext, &Pkcs7).unwrap();
+        let encoded = base64_encode(&hex_encode(&ciphertext)).unwrap();
+        assert_eq!(decrypt_base64(&encoded, key).unwrap(), plaintext.to_vec());
+    }
+
+    #[test]
+    fn test_decrypt_challenge_file() {
+        let encoded = fs::read_to_string(Path::new("src/challenge7/7.txt")).unwrap();
+        let res = decrypt_base64(&encoded, b"YELLOW SUBMARINE");
+        assert!(res.is_ok());
+        let plaintext = String::from_utf8(res.unwrap()).unwrap();
+        assert_eq!(plaintext.len(), 2876);
+        assert!(plaintext.starts_with("I'm back and I'm ringin' the bell"));
+        assert!(plaintext.ends_with("Play that funky music \n"));
+    }
+}
@@ -0,0 +1,212 @@
+/*
+Detect AES in ECB mode
+
+In this file are a bunch of hex-encoded ciphertexts.
+
+One of them has been encrypted with ECB.
+
+Detect it.
+
+Remember that the problem with ECB is that it is stateless and deterministic; the same 16 byte
+plaintext block will always produce the same 16 byte ciphertext.
+*/
+
+use crate::aes::BLOCK_SIZE;
+use crate::challenge1::hex_decode;
+use crate::challenge4::hex_lines;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepeatedBlock {
+    pub block: Vec<u8>,
+    // Indices of the blocks holding it, in order
+    pub positions: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockRepetition {
+    pub block_size: usize,
+    // Whole blocks looked at, a ragged tail is ignored
+    pub blocks: usize,
+    // Blocks equal to an earlier block
+    pub duplicates: usize,
+    // Share of all pairs of blocks that are equal, comparable across inputs of different length
+    pub collision_rate: f64,
+    // In order of first appearance
+    pub repeated: Vec<RepeatedBlock>,
+}
+
+impl BlockRepetition {
+    pub fn is_likely_ecb(&self) -> bool {
+        self.duplicates > 0
+    }
+}
+
+// Find the blocks of `block_size` bytes that appear more than once. `block_size` must not be 0.
+pub fn block_repetition(data: &[u8], block_size: usize) -> BlockRepetition {
+    let mut positions: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    let mut first_seen: Vec<&[u8]> = vec![];
+    let blocks: Vec<&[u8]> = data.chunks_exact(block_size).collect();
+    for (index, block) in blocks.iter().enumerate() {
+        let seen = positions.entry(block).or_default();
+        if seen.is_empty() {
+            first_seen.push(block);
+        }
+        seen.push(index);
+    }
+    let repeated: Vec<RepeatedBlock> = first_seen
+        .into_iter()
+        .filter(|block| positions[block].len() > 1)
+        .map(|block| RepeatedBlock {
+            block: block.to_vec(),
+            positions: positions[block].clone(),
+        })
+        .collect();
+    let equal_pairs: usize = repeated
+        .iter()
+        .map(|r| r.positions.len() * (r.positions.len() - 1) / 2)
+        .sum();
+    let pairs = blocks.len() * blocks.len().saturating_sub(1) / 2;
+    BlockRepetition {
+        block_size,
+        blocks: blocks.len(),
+        duplicates: blocks.len() - positions.len(),
+        collision_rate: if pairs == 0 {
+            0.0
+        } else {
+            equal_pairs as f64 / pairs as f64
+        },
+        repeated,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineReport {
+    pub line_number: usize,
+    pub repetition: BlockRepetition,
+}
+
+// Blocks of every hex encoded line of `reader`, most repeated first: by duplicate count, then by
+// collision rate, then earlier lines first. Lines that are empty, not UTF-8 or not hex are skipped
+// and line numbers start at 1.
+pub fn rank_ecb_lines<R: BufRead>(reader: R, block_size: usize) -> io::Result<Vec<LineReport>> {
+    let mut reports: Vec<LineReport> = vec![];
+    for numbered_line in hex_lines(reader) {
+        let (line_number, line) = numbered_line?;
+        if !line.len().is_multiple_of(2) {
+            continue;
+        }
+        if let Ok(bytes) = hex_decode(&line) {
+            reports.push(LineReport {
+                line_number,
+                repetition: block_repetition(&bytes, block_size),
+            });
+        }
+    }
+    reports.sort_by(|a, b| {
+        b.repetition
+            .duplicates
+            .cmp(&a.repetition.duplicates)
+            .then_with(|| {
+                b.repetition
+                    .collision_rate
+                    .total_cmp(&a.repetition.collision_rate)
+            })
+            .then_with(|| a.line_number.cmp(&b.line_number))
+    });
+    Ok(reports)
+}
+
+// Lines with at least one repeated AES block, most repeated first
+pub fn detect_ecb_lines<R: BufRead>(reader: R) -> io::Result<Vec<LineReport>> {
+    Ok(rank_ecb_lines(reader, BLOCK_SIZE)?
+        .into_iter()
+        .filter(|report| report.repetition.is_likely_ecb())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_cipher::XorCipher;
+    use crate::challenge1::hex_encode;
+    use crate::challenge7::{aes_128_ecb_encrypt, ecb_encrypt};
+    use crate::challenge9::{NoPadding, Pkcs7};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_block_repetition() {
+        let res = block_repetition(b"abcdXXXXefghXXXXabcdXXXXij", 4);
+        assert_eq!(res.blocks, 6);
+        assert_eq!(res.duplicates, 3);
+        assert_eq!(
+            res.repeated,
+            vec![
+                RepeatedBlock {
+                    block: b"abcd".to_vec(),
+                    positions: vec![0, 4]
+                },
+                RepeatedBlock {
+                    block: b"XXXX".to_vec(),
+                    positions: vec![1, 3, 5]
+                },
+            ]
+        );
+        // 1 + 3 equal pairs out of 15
+        assert!((res.collision_rate - 4.0 / 15.0).abs() < 1e-12);
+        let res = block_repetition(b"abc", 4);
+        assert_eq!((res.blocks, res.duplicates), (0, 0));
+        assert!(!res.is_likely_ecb());
+    }
+
+    #[test]
+    fn test_block_repetition_any_block_size() {
+        let cipher = XorCipher::new(b"8 bytes!").unwrap();
+        let plaintext = b"same oldnew blk!same oldsame old";
+        let ciphertext = ecb_encrypt(&cipher, plaintext, &Pkcs7).unwrap();
+        let res = block_repetition(&ciphertext, 8);
+        assert!(res.is_likely_ecb());
+        assert_eq!(res.repeated.len(), 1);
+        assert_eq!(res.repeated[0].positions, vec![0, 2, 3]);
+        assert!(!block_repetition(&ciphertext, 16).is_likely_ecb());
+    }
+
+    #[test]
+    fn test_detect_ecb_lines() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut lines: Vec<String> = (0..50)
+            .map(|_| {
+                let bytes: Vec<u8> = (0..160).map(|_| rng.gen()).collect();
+                hex_encode(&bytes)
+            })
+            .collect();
+        let plaintext = b"YELLOW SUBMARINEyellow submarineYELLOW SUBMARINE".repeat(3);
+        let ecb = aes_128_ecb_encrypt(b"0123456789abcdef", &plaintext, &NoPadding);
+        lines[31] = hex_encode(&ecb.unwrap());
+        lines.push("not hex".to_string());
+        let res = detect_ecb_lines(lines.join("\n").as_bytes());
+        assert!(res.is_ok());
+        let reports = res.unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].line_number, 32);
+        assert_eq!(reports[0].repetition.duplicates, 7);
+        assert_eq!(reports[0].repetition.repeated[1].positions, vec![1, 4, 7]);
+        let ranked = rank_ecb_lines(lines.join("\n").as_bytes(), BLOCK_SIZE).unwrap();
+        assert_eq!(ranked.len(), 50);
+    }
+
+    #[test]
+    fn test_rank_ecb_lines_skips_non_utf8_lines() {
+        let ecb = aes_128_ecb_encrypt(b"0123456789abcdef", &[b'A'; 64], &NoPadding).unwrap();
+        let mut input = b"\xff\xfe\n".to_vec();
+        input.extend_from_slice(hex_encode(&ecb).as_bytes());
+        let res = rank_ecb_lines(&input[..], BLOCK_SIZE);
+        assert!(res.is_ok());
+        let reports = res.unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].line_number, 2);
+        assert_eq!(reports[0].repetition.duplicates, 3);
+    }
+}
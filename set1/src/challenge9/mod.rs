@@ -0,0 +1,459 @@
+/*
+Implement PKCS#7 padding
+
+A block cipher transforms a fixed-sized block (usually 8 or 16 bytes) of plaintext into ciphertext.
+But we almost never want to transform a single block; we encrypt irregularly-sized messages.
+
+One way we account for irregularly-sized messages is by padding, creating a plaintext that is an
+even multiple of the blocksize. The most popular padding scheme is called PKCS#7.
+
+So: pad any block to a specific block length, by appending the number of bytes of padding to the
+end of the block. For instance,
+
+"YELLOW SUBMARINE"
+
+... padded to 20 bytes would be:
+
+"YELLOW SUBMARINE\x04\x04\x04\x04"
+
+Other schemes are met in the wild, all behind the Padding trait: ANSI X.923 (zeros then the length
+byte), ISO 10126 (random bytes then the length byte), ISO/IEC 7816-4 (0x80 then zeros), zero
+padding and no padding at all. Zero padding is ambiguous, data ending in zero bytes can't be told
+apart from its padding, and no padding only works with data that's already a whole number of blocks.
+*/
+
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingError {
+    // Schemes ending in a length byte work with blocks of 1 to 255 bytes, the others of any size
+    InvalidBlockSize(usize),
+    InvalidLength { len: usize, block_size: usize },
+    ZeroPadding,
+    PaddingTooLong { pad: usize, block_size: usize },
+    InconsistentPadding,
+    // ISO/IEC 7816-4 padding without its 0x80 byte
+    MissingMarker,
+}
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaddingError::InvalidBlockSize(block_size) => {
+                write!(f, "invalid block size for the padding: {}", block_size)
+            }
+            PaddingError::InvalidLength { len, block_size } => write!(
+                f,
+                "length {} is not a valid multiple of the block size {}",
+                len, block_size
+            ),
+            PaddingError::ZeroPadding => write!(f, "padding byte is 0"),
+            PaddingError::PaddingTooLong { pad, block_size } => write!(
+                f,
+                "padding of {} bytes is longer than the block size {}",
+                pad, block_size
+            ),
+            PaddingError::InconsistentPadding => write!(f, "padding bytes differ"),
+            PaddingError::MissingMarker => write!(f, "no 0x80 byte before the padding zeros"),
+        }
+    }
+}
+
+impl Error for PaddingError {}
+
+pub trait Padding {
+    // Extend data to a whole number of blocks
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, PaddingError>;
+
+    // The data without its padding, `data` must be a whole number of blocks
+    fn unpad<'a>(&self, data: &'a [u8], block_size: usize) -> Result<&'a [u8], PaddingError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pkcs7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnsiX923;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iso10126;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iso7816;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroBytes;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoPadding;
+
+fn check_block_size(block_size: usize) -> Result<(), PaddingError> {
+    if block_size == 0 || block_size > u8::MAX as usize {
+        return Err(PaddingError::InvalidBlockSize(block_size));
+    }
+    Ok(())
+}
+
+fn check_any_block_size(block_size: usize) -> Result<(), PaddingError> {
+    if block_size == 0 {
+        return Err(PaddingError::InvalidBlockSize(block_size));
+    }
+    Ok(())
+}
+
+fn check_padded_length(data: &[u8], block_size: usize) -> Result<(), PaddingError> {
+    if data.is_empty() || !data.len().is_multiple_of(block_size) {
+        return Err(PaddingError::InvalidLength {
+            len: data.len(),
+            block_size,
+        });
+    }
+    Ok(())
+}
+
+// Between 1 and block_size bytes of padding, filled by `fill` except for the last byte which holds
+// the number of bytes added
+fn pad_with_length<F: FnMut() -> u8>(
+    data: &[u8],
+    block_size: usize,
+    mut fill: F,
+) -> Result<Vec<u8>, PaddingError> {
+    check_block_size(block_size)?;
+    let pad = block_size - data.len() % block_size;
+    let mut padded = Vec::with_capacity(data.len() + pad);
+    padded.extend_from_slice(data);
+    padded.extend((1..pad).map(|_| fill()));
+    padded.push(pad as u8);
+    Ok(padded)
+}
+
+// Number of padding bytes given by the last byte
+fn padding_length(data: &[u8], block_size: usize) -> Result<usize, PaddingError> {
+    check_block_size(block_size)?;
+    check_padded_length(data, block_size)?;
+    let pad = data[data.len() - 1] as usize;
+    if pad == 0 {
+        return Err(PaddingError::ZeroPadding);
+    }
+    if pad > block_size {
+        return Err(PaddingError::PaddingTooLong { pad, block_size });
+    }
+    Ok(pad)
+}
+
+impl Padding for Pkcs7 {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, PaddingError> {
+        pad(data, block_size)
+    }
+
+    fn unpad<'a>(&self, data: &'a [u8], block_size: usize) -> Result<&'a [u8], PaddingError> {
+        unpad(data, block_size)
+    }
+}
+
+impl Padding for AnsiX923 {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, PaddingError> {
+        pad_with_length(data, block_size, || 0)
+    }
+
+    fn unpad<'a>(&self, data: &'a [u8], block_size: usize) -> Result<&'a [u8], PaddingError> {
+        let pad = padding_length(data, block_size)?;
+        let (unpadded, padding) = data.split_at(data.len() - pad);
+        if padding[..pad - 1].iter().any(|&b| b != 0) {
+            return Err(PaddingError::InconsistentPadding);
+        }
+        Ok(unpadded)
+    }
+}
+
+impl Padding for Iso10126 {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, PaddingError> {
+        let mut rng = rand::thread_rng();
+        pad_with_length(data, block_size, || rng.gen())
+    }
+
+    // Only the length byte can be checked
+    fn unpad<'a>(&self, data: &'a [u8], block_size: usize) -> Result<&'a [u8], PaddingError> {
+        let pad = padding_length(data, block_size)?;
+        Ok(&data[..data.len() - pad])
+    }
+}
+
+impl Padding for Iso7816 {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, PaddingError> {
+        check_any_block_size(block_size)?;
+        let pad = block_size - data.len() % block_size;
+        let mut padded = Vec::with_capacity(data.len() + pad);
+        padded.extend_from_slice(data);
+        padded.push(0x80);
+        padded.resize(data.len() + pad, 0);
+        Ok(padded)
+    }
+
+    fn unpad<'a>(&self, data: &'a [u8], block_size: usize) -> Result<&'a [u8], PaddingError> {
+        check_any_block_size(block_size)?;
+        check_padded_length(data, block_size)?;
+        let last_block = &data[data.len() - block_size..];
+        match last_block.iter().rposition(|&b| b != 0) {
+            Some(i) if last_block[i] == 0x80 => Ok(&data[..data.len() - block_size + i]),
+            _ => Err(PaddingError::MissingMarker),
+        }
+    }
+}
+
+impl Padding for ZeroBytes {
+    // Adds nothing to data that's already a whole number of blocks
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, PaddingError> {
+        check_any_block_size(block_size)?;
+        let mut padded = data.to_vec();
+        padded.resize(data.len().div_ceil(block_size) * block_size, 0);
+        Ok(padded)
+    }
+
+    // Strips every trailing zero of the last block, whether it was padding or data
+    fn unpad<'a>(&self, data: &'a [u8], block_size: usize) -> Result<&'a [u8], PaddingError> {
+        check_any_block_size(block_size)?;
+        if !data.len().is_multiple_of(block_size) {
+            return Err(PaddingError::InvalidLength {
+                len: data.len(),
+                block_size,
+            });
+        }
+        let block_start = data.len().saturating_sub(block_size);
+        let zeros = data[block_start..]
+            .iter()
+            .rev()
+            .take_while(|&&b| b == 0)
+            .count();
+        Ok(&data[..data.len() - zeros])
+    }
+}
+
+impl Padding for NoPadding {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, PaddingError> {
+        self.unpad(data, block_size).map(<[u8]>::to_vec)
+    }
+
+    fn unpad<'a>(&self, data: &'a [u8], block_size: usize) -> Result<&'a [u8], PaddingError> {
+        check_any_block_size(block_size)?;
+        if !data.len().is_multiple_of(block_size) {
+            return Err(PaddingError::InvalidLength {
+                len: data.len(),
+                block_size,
+            });
+        }
+        Ok(data)
+    }
+}
+
+// Always adds between 1 and block_size bytes, each holding the number of bytes added
+pub fn pad(data: &[u8], block_size: usize) -> Result<Vec<u8>, PaddingError> {
+    pad_with_length(data, block_size, || {
+        (block_size - data.len() % block_size) as u8
+    })
+}
+
+// All ones when a <= b, 0 otherwise, without branching. Both must be below 2^31.
+fn mask_le(a: u32, b: u32) -> u32 {
+    (b.wrapping_sub(a) >> 31).wrapping_sub(1)
+}
+
+// The data without its padding. Every byte of the last block is looked at whatever the padding, so
+// the time taken only depends on the length of the input. The error variant does tell what was
+// wrong: callers that mustn't be a padding oracle have to report every error the same way.
+pub fn unpad(data: &[u8], block_size: usize) -> Result<&[u8], PaddingError> {
+    check_block_size(block_size)?;
+    check_padded_length(data, block_size)?;
+    let last_block = &data[data.len() - block_size..];
+    let pad = last_block[block_size - 1] as u32;
+    let mut mismatch: u32 = 0;
+    for (i, &byte) in last_block.iter().enumerate() {
+        let in_padding = mask_le((block_size - i) as u32, pad);
+        mismatch |= in_padding & (byte as u32 ^ pad);
+    }
+    let zero = mask_le(pad, 0);
+    let too_long = !mask_le(pad, block_size as u32);
+    if zero != 0 {
+        Err(PaddingError::ZeroPadding)
+    } else if too_long != 0 {
+        Err(PaddingError::PaddingTooLong {
+            pad: pad as usize,
+            block_size,
+        })
+    } else if mismatch != 0 {
+        Err(PaddingError::InconsistentPadding)
+    } else {
+        Ok(&data[..data.len() - pad as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(
+            pad(b"YELLOW SUBMARINE", 20),
+            Ok(b"YELLOW SUBMARINE\x04\x04\x04\x04".to_vec())
+        );
+        assert_eq!(
+            pad(b"YELLOW SUBMARINE", 16),
+            Ok([&b"YELLOW SUBMARINE"[..], &[16u8; 16]].concat())
+        );
+        assert_eq!(pad(b"", 3), Ok(vec![3, 3, 3]));
+        assert_eq!(pad(b"abc", 0), Err(PaddingError::InvalidBlockSize(0)));
+        assert_eq!(pad(b"abc", 256), Err(PaddingError::InvalidBlockSize(256)));
+    }
+
+    #[test]
+    fn test_unpad() {
+        for len in 0..40 {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let padded = pad(&data, 16).unwrap();
+            assert_eq!(unpad(&padded, 16), Ok(&data[..]));
+        }
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x04\x04\x04\x04", 16),
+            Ok(&b"ICE ICE BABY"[..])
+        );
+    }
+
+    #[test]
+    fn test_unpad_errors() {
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x05\x05\x05\x05", 16),
+            Err(PaddingError::InconsistentPadding)
+        );
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x01\x02\x03\x04", 16),
+            Err(PaddingError::InconsistentPadding)
+        );
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x04\x04\x04\x00", 16),
+            Err(PaddingError::ZeroPadding)
+        );
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x11\x11\x11\x11", 16),
+            Err(PaddingError::PaddingTooLong {
+                pad: 17,
+                block_size: 16
+            })
+        );
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x04\x04\x04", 16),
+            Err(PaddingError::InvalidLength {
+                len: 15,
+                block_size: 16
+            })
+        );
+        assert_eq!(
+            unpad(b"", 16),
+            Err(PaddingError::InvalidLength {
+                len: 0,
+                block_size: 16
+            })
+        );
+    }
+
+    fn schemes() -> Vec<(&'static str, Box<dyn Padding>)> {
+        vec![
+            ("PKCS#7", Box::new(Pkcs7)),
+            ("ANSI X.923", Box::new(AnsiX923)),
+            ("ISO 10126", Box::new(Iso10126)),
+            ("ISO/IEC 7816-4", Box::new(Iso7816)),
+            ("zero", Box::new(ZeroBytes)),
+        ]
+    }
+
+    #[test]
+    fn test_padding_schemes() {
+        let data = b"YELLOW SUBMARINE!";
+        assert_eq!(
+            AnsiX923.pad(data, 20),
+            Ok(b"YELLOW SUBMARINE!\0\0\x03".to_vec())
+        );
+        assert_eq!(
+            Iso7816.pad(data, 20),
+            Ok(b"YELLOW SUBMARINE!\x80\0\0".to_vec())
+        );
+        assert_eq!(
+            ZeroBytes.pad(data, 20),
+            Ok(b"YELLOW SUBMARINE!\0\0\0".to_vec())
+        );
+        let padded = Iso10126.pad(data, 20).unwrap();
+        assert_eq!((padded.len(), padded[19]), (20, 3));
+        assert_eq!(NoPadding.pad(&data[..16], 16), Ok(data[..16].to_vec()));
+        assert_eq!(
+            NoPadding.pad(data, 16),
+            Err(PaddingError::InvalidLength {
+                len: 17,
+                block_size: 16
+            })
+        );
+        for (name, scheme) in schemes() {
+            let padded = scheme.pad(data, 20).unwrap();
+            assert_eq!(scheme.unpad(&padded, 20), Ok(&data[..]), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_padding_scheme_errors() {
+        assert_eq!(
+            AnsiX923.unpad(b"ICE ICE BABY\0\x01\0\x04", 16),
+            Err(PaddingError::InconsistentPadding)
+        );
+        assert_eq!(
+            AnsiX923.unpad(b"ICE ICE BABY\0\0\0\x11", 16),
+            Err(PaddingError::PaddingTooLong {
+                pad: 17,
+                block_size: 16
+            })
+        );
+        assert_eq!(
+            Iso10126.unpad(b"ICE ICE BABY\x07\x01\x02\x00", 16),
+            Err(PaddingError::ZeroPadding)
+        );
+        assert_eq!(
+            Iso7816.unpad(b"ICE ICE BABY\x04\0\0\0", 16),
+            Err(PaddingError::MissingMarker)
+        );
+        assert_eq!(
+            Iso7816.unpad(&[0u8; 16], 16),
+            Err(PaddingError::MissingMarker)
+        );
+    }
+
+    #[test]
+    fn test_padding_ambiguity() {
+        // Messages that end like padding would
+        let messages: Vec<&[u8]> = vec![
+            b"",
+            b"data",
+            b"data\0",
+            b"data\0\0",
+            b"data\x80",
+            b"data\x01",
+            b"data\x80\0",
+            b"0123456789abcdef",
+            b"0123456789abcde\0",
+        ];
+        let mut ambiguous: Vec<&str> = vec![];
+        for (name, scheme) in schemes() {
+            let round_trips = messages.iter().all(|message| {
+                let padded = scheme.pad(message, 16).unwrap();
+                scheme.unpad(&padded, 16) == Ok(*message)
+            });
+            if !round_trips {
+                ambiguous.push(name);
+            }
+        }
+        assert_eq!(ambiguous, vec!["zero"]);
+        // Zero padding gives different messages the same padded form
+        assert_eq!(ZeroBytes.pad(b"data", 8), ZeroBytes.pad(b"data\0", 8));
+        // The length byte schemes always pad, so a message that looks padded stays apart
+        assert_ne!(Pkcs7.pad(b"data", 8), Pkcs7.pad(b"data\x04\x04\x04\x04", 8));
+    }
+}
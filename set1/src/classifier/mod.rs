@@ -0,0 +1,222 @@
+/*
+Automatic ciphertext classifier
+
+Given an unknown blob, guess its outer encoding (hex, base64 or raw bytes) and how likely it is to
+come from each of the cipher families seen so far:
+
+    - single-byte XOR: the plaintext statistics survive, one key byte undoes them
+    - repeating-key XOR: every KEYSIZE-th byte looks like single-byte XOR
+    - ECB block cipher: identical plaintext blocks give identical ciphertext blocks
+    - stream cipher, CBC or random data: all three look uniformly random, block alignment alone
+      can't tell them apart, so they are reported as one family
+
+Confidences are heuristics built on English scoring, index of coincidence and keysize search,
+normalized so they add up to 1.
+*/
+
+use crate::blocks::transpose;
+use crate::challenge1::{hex_decode, hex_encode};
+use crate::challenge3::find_message_and_key;
+use crate::challenge6::{base64_decode_bytes, find_keysize, index_of_coincidence};
+use crate::challenge8::block_repetition;
+
+// Average per-byte English score of English text and of the best decryption of random bytes
+const ENGLISH_SCORE: f64 = 0.07;
+const RANDOM_SCORE: f64 = 0.01;
+// Index of coincidence of English text and of uniformly random bytes
+const ENGLISH_IOC: f64 = 0.065;
+const RANDOM_IOC: f64 = 1.0 / 256.0;
+const BLOCK_SIZE: usize = 16;
+// Fewest bytes per column for a keysize to be considered
+const MIN_COLUMN_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Hex,
+    Base64,
+    Raw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CipherFamily {
+    SingleByteXor,
+    RepeatingKeyXor,
+    EcbBlockCipher,
+    StreamCbcOrRandom,
+}
+
+#[derive(Debug, Clone)]
+pub struct Classification {
+    pub encoding: Encoding,
+    pub decoded: Vec<u8>,
+    // Every family with its confidence, most likely first
+    pub families: Vec<(CipherFamily, f64)>,
+}
+
+impl Classification {
+    pub fn most_likely(&self) -> CipherFamily {
+        self.families[0].0
+    }
+
+    pub fn confidence(&self, family: CipherFamily) -> f64 {
+        self.families
+            .iter()
+            .find(|(f, _)| *f == family)
+            .map_or(0.0, |(_, confidence)| *confidence)
+    }
+}
+
+fn clamp_unit(value: f64) -> f64 {
+    value.clamp(0.0, 1.0)
+}
+
+// Guess the outer encoding and return it together with the decoded bytes
+pub fn detect_encoding(blob: &[u8]) -> (Encoding, Vec<u8>) {
+    let text = match std::str::from_utf8(blob) {
+        Ok(text) => text,
+        Err(_) => return (Encoding::Raw, blob.to_vec()),
+    };
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.is_empty() {
+        return (Encoding::Raw, blob.to_vec());
+    }
+    if compact.len().is_multiple_of(2) && compact.bytes().all(|b| b.is_ascii_hexdigit()) {
+        if let Ok(decoded) = hex_decode(&compact) {
+            return (Encoding::Hex, decoded);
+        }
+    }
+    let data = compact.trim_end_matches('=');
+    let is_base64 = compact.len().is_multiple_of(4)
+        && compact.len() - data.len() <= 2
+        && data
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/');
+    if is_base64 {
+        if let Ok(decoded) = base64_decode_bytes(&compact) {
+            return (Encoding::Base64, decoded);
+        }
+    }
+    (Encoding::Raw, blob.to_vec())
+}
+
+fn single_byte_xor_score(bytes: &[u8]) -> f64 {
+    let bundle = find_message_and_key(&hex_encode(bytes));
+    bundle.probability / bytes.len() as f64
+}
+
+// Average index of coincidence of the columns for the most likely keysize
+fn best_keysize_column_ioc(bytes: &[u8]) -> Option<f64> {
+    let max_keysize = (bytes.len() / MIN_COLUMN_LEN).min(40);
+    if max_keysize < 2 {
+        return None;
+    }
+    let keysize = find_keysize(bytes, 2..=max_keysize).first()?.keysize;
+    let columns = transpose(bytes, keysize);
+    Some(columns.iter().map(|c| index_of_coincidence(c)).sum::<f64>() / keysize as f64)
+}
+
+// Confidence per cipher family for already decoded bytes, most likely first
+pub fn classify_bytes(bytes: &[u8]) -> Vec<(CipherFamily, f64)> {
+    let mut scores: Vec<(CipherFamily, f64)> = vec![];
+    if bytes.len() >= 2 {
+        let ioc = index_of_coincidence(bytes);
+        let single = clamp_unit(
+            (single_byte_xor_score(bytes) - RANDOM_SCORE) / (ENGLISH_SCORE - RANDOM_SCORE),
+        );
+        let ecb = 1.0 - 0.5f64.powi(block_repetition(bytes, BLOCK_SIZE).duplicates as i32);
+        // Repeated blocks also inflate the column statistics at multiples of the block size
+        let repeating = best_keysize_column_ioc(bytes).map_or(0.0, |column_ioc| {
+            clamp_unit((column_ioc - ioc) / (ENGLISH_IOC - RANDOM_IOC)) * (1.0 - ecb)
+        });
+        let randomness = clamp_unit((ENGLISH_IOC - ioc) / (ENGLISH_IOC - RANDOM_IOC))
+            * (1.0 - repeating)
+            * (1.0 - ecb);
+        scores.push((CipherFamily::SingleByteXor, single));
+        scores.push((CipherFamily::RepeatingKeyXor, repeating));
+        scores.push((CipherFamily::EcbBlockCipher, ecb));
+        scores.push((CipherFamily::StreamCbcOrRandom, randomness));
+    }
+    let total: f64 = scores.iter().map(|(_, score)| score).sum();
+    if total == 0.0 {
+        return vec![
+            (CipherFamily::StreamCbcOrRandom, 1.0),
+            (CipherFamily::SingleByteXor, 0.0),
+            (CipherFamily::RepeatingKeyXor, 0.0),
+            (CipherFamily::EcbBlockCipher, 0.0),
+        ];
+    }
+    for (_, score) in scores.iter_mut() {
+        *score /= total;
+    }
+    scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    scores
+}
+
+// Guess the outer encoding of the blob and the cipher family of its contents
+pub fn classify(blob: &[u8]) -> Classification {
+    let (encoding, decoded) = detect_encoding(blob);
+    let families = classify_bytes(&decoded);
+    Classification {
+        encoding,
+        decoded,
+        families,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn pseudo_random_bytes(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_classify_single_byte_xor() {
+        let input = b"1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
+        let res = classify(input);
+        assert_eq!(res.encoding, Encoding::Hex);
+        assert_eq!(res.decoded.len(), 34);
+        assert_eq!(res.most_likely(), CipherFamily::SingleByteXor);
+    }
+
+    #[test]
+    fn test_classify_repeating_key_xor() {
+        let input = fs::read("src/challenge6/6.txt").unwrap();
+        let res = classify(&input);
+        assert_eq!(res.encoding, Encoding::Base64);
+        assert_eq!(res.most_likely(), CipherFamily::RepeatingKeyXor);
+    }
+
+    #[test]
+    fn test_classify_ecb() {
+        let block = pseudo_random_bytes(BLOCK_SIZE, 7);
+        let mut input = pseudo_random_bytes(BLOCK_SIZE * 3, 1);
+        for _ in 0..4 {
+            input.extend_from_slice(&block);
+        }
+        let res = classify(&input);
+        assert_eq!(res.encoding, Encoding::Raw);
+        assert_eq!(res.most_likely(), CipherFamily::EcbBlockCipher);
+    }
+
+    #[test]
+    fn test_classify_random() {
+        // Block aligned or not, random bytes land in the same family
+        for &len in &[1000, 1024] {
+            let res = classify(&pseudo_random_bytes(len, 42));
+            assert_eq!(res.encoding, Encoding::Raw);
+            assert_eq!(res.most_likely(), CipherFamily::StreamCbcOrRandom);
+            assert!(res.confidence(CipherFamily::StreamCbcOrRandom) > 0.8);
+            let total: f64 = res.families.iter().map(|(_, c)| c).sum();
+            assert!((total - 1.0).abs() < 1e-9);
+        }
+    }
+}
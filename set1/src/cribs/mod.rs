@@ -0,0 +1,216 @@
+/*
+Known-plaintext cribs for breaking repeating-key XOR
+
+Lots of XOR obfuscated data is a file with a well known header: a PNG image, a PDF, a ZIP archive,
+an ELF binary, an XML document, a PEM encoded key... Assuming the plaintext starts with one of those
+headers gives the key bytes under it for free: key[i % KEYSIZE] = ciphertext[i] ^ header[i]. When
+the header is longer than the key, the repeated key positions must agree, which tells the right
+header apart from the wrong ones. Key bytes the header doesn't reach are filled in by solving their
+column as single-character XOR.
+*/
+
+use crate::blocks::transpose;
+use crate::challenge1::hex_encode;
+use crate::challenge3::{find_message_and_key, ngram_score};
+use crate::challenge5::repeating_xor_bytes;
+use crate::challenge6::find_keysize;
+
+#[derive(Debug)]
+pub struct Crib {
+    pub name: &'static str,
+    pub header: &'static [u8],
+}
+
+pub const CRIBS: &[Crib] = &[
+    Crib {
+        name: "PNG",
+        header: b"\x89PNG\r\n\x1a\n",
+    },
+    Crib {
+        name: "PDF",
+        header: b"%PDF-1.",
+    },
+    Crib {
+        name: "ZIP",
+        header: b"PK\x03\x04",
+    },
+    Crib {
+        name: "ELF",
+        header: b"\x7fELF",
+    },
+    Crib {
+        name: "GZIP",
+        header: b"\x1f\x8b\x08",
+    },
+    Crib {
+        name: "JPEG",
+        header: b"\xff\xd8\xff",
+    },
+    Crib {
+        name: "GIF",
+        header: b"GIF8",
+    },
+    Crib {
+        name: "XML",
+        header: b"<?xml version=\"1.",
+    },
+    Crib {
+        name: "PEM",
+        header: b"-----BEGIN ",
+    },
+    Crib {
+        name: "Shebang",
+        header: b"#!/",
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct CribMatch {
+    pub crib: &'static Crib,
+    pub key: Vec<u8>,
+    // Key positions taken from the crib rather than from statistics
+    pub known: Vec<bool>,
+    // Crib bytes that fell on an already known key position and agreed with it
+    pub verified_bytes: usize,
+    pub plaintext: Vec<u8>,
+}
+
+// Key bytes implied by `header` being at the start of the plaintext, None for the key positions the
+// header doesn't reach. Returns None when the header implies two different bytes for a position.
+pub fn key_from_header(
+    ciphertext: &[u8],
+    header: &[u8],
+    keysize: usize,
+) -> Option<Vec<Option<u8>>> {
+    if keysize == 0 || header.len() > ciphertext.len() {
+        return None;
+    }
+    let mut key: Vec<Option<u8>> = vec![None; keysize];
+    for (i, (c, p)) in ciphertext.iter().zip(header.iter()).enumerate() {
+        let key_byte = c ^ p;
+        match key[i % keysize] {
+            Some(known) if known != key_byte => return None,
+            _ => key[i % keysize] = Some(key_byte),
+        }
+    }
+    Some(key)
+}
+
+// Solve the ciphertext assuming the plaintext starts with the crib's header and the key is
+// `keysize` bytes long
+pub fn solve_with_crib(
+    ciphertext: &[u8],
+    crib: &'static Crib,
+    keysize: usize,
+) -> Option<CribMatch> {
+    let partial_key = key_from_header(ciphertext, crib.header, keysize)?;
+    let columns = transpose(ciphertext, keysize);
+    let key: Vec<u8> = partial_key
+        .iter()
+        .zip(columns.iter())
+        .map(|(known, column)| {
+            known.unwrap_or_else(|| find_message_and_key(&hex_encode(column)).key)
+        })
+        .collect();
+    Some(CribMatch {
+        crib,
+        known: partial_key.iter().map(Option::is_some).collect(),
+        verified_bytes: crib.header.len().saturating_sub(keysize),
+        plaintext: repeating_xor_bytes(ciphertext, &key),
+        key,
+    })
+}
+
+// Keysize estimates tried by solve_with_cribs, most likely first
+const CANDIDATE_KEYSIZES: usize = 3;
+
+// The most likely keysizes and all their divisors. The estimate often ranks multiples of the real
+// keysize first, a crib tells them apart since only the real one repeats under the header.
+fn candidate_keysizes(ciphertext: &[u8]) -> Vec<usize> {
+    let mut keysizes: Vec<usize> = vec![];
+    for estimate in find_keysize(ciphertext, 2..=40)
+        .iter()
+        .take(CANDIDATE_KEYSIZES)
+    {
+        for keysize in (2..=estimate.keysize).filter(|k| estimate.keysize % k == 0) {
+            if !keysizes.contains(&keysize) {
+                keysizes.push(keysize);
+            }
+        }
+    }
+    keysizes
+}
+
+// Try every crib in CRIBS with each candidate keysize. Matches whose header was checked against
+// more repeated key positions come first, then the most English looking plaintext.
+pub fn solve_with_cribs(ciphertext: &[u8]) -> Vec<CribMatch> {
+    let mut matches: Vec<CribMatch> = candidate_keysizes(ciphertext)
+        .into_iter()
+        .flat_map(|keysize| {
+            CRIBS
+                .iter()
+                .filter_map(move |crib| solve_with_crib(ciphertext, crib, keysize))
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.verified_bytes
+            .cmp(&a.verified_bytes)
+            .then_with(|| ngram_score(&b.plaintext).total_cmp(&ngram_score(&a.plaintext)))
+    });
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenge7::lyrics;
+
+    #[test]
+    fn test_key_from_header() {
+        let ciphertext = repeating_xor_bytes(b"\x7fELF\x02\x01\x01", b"key");
+        let key = key_from_header(&ciphertext, b"\x7fELF", 3);
+        assert_eq!(key, Some(vec![Some(b'k'), Some(b'e'), Some(b'y')]));
+        assert_eq!(
+            key_from_header(&ciphertext, b"\x7fELF", 5).unwrap()[4],
+            None
+        );
+        assert_eq!(key_from_header(&ciphertext, b"PK\x03\x04", 3), None);
+    }
+
+    #[test]
+    fn test_candidate_keysizes() {
+        let mut plaintext = b"-----BEGIN LYRICS-----\n".to_vec();
+        plaintext.extend(lyrics());
+        let ciphertext = repeating_xor_bytes(&plaintext, b"SECRETS");
+        // Multiples of 7 rank first, 7 itself only comes in as their divisor
+        let keysizes = candidate_keysizes(&ciphertext);
+        assert!(keysizes.contains(&7));
+        assert!(keysizes.contains(&14));
+    }
+
+    #[test]
+    fn test_solve_with_cribs() {
+        let mut plaintext = b"-----BEGIN LYRICS-----\n".to_vec();
+        plaintext.extend(lyrics());
+        let ciphertext = repeating_xor_bytes(&plaintext, b"SECRETS");
+        let matches = solve_with_cribs(&ciphertext);
+        assert!(!matches.is_empty());
+        assert_eq!(matches[0].crib.name, "PEM");
+        assert_eq!(matches[0].verified_bytes, 4);
+        assert_eq!(matches[0].key, b"SECRETS".to_vec());
+        assert_eq!(matches[0].plaintext, plaintext);
+    }
+
+    #[test]
+    fn test_solve_with_crib_fills_unknown_key_bytes() {
+        let mut plaintext = b"%PDF-1.4\n".to_vec();
+        plaintext.extend(lyrics());
+        let key = b"Portable Document";
+        let ciphertext = repeating_xor_bytes(&plaintext, key);
+        let res = solve_with_crib(&ciphertext, &CRIBS[1], key.len());
+        assert!(res.is_some());
+        let crib_match = res.unwrap();
+        assert_eq!(crib_match.known.iter().filter(|&&k| k).count(), 7);
+        assert_eq!(crib_match.key, key.to_vec());
+    }
+}
@@ -0,0 +1,342 @@
+/*
+Galois/Counter Mode (GCM)
+
+Authenticated encryption from NIST SP 800-38D: CTR mode encryption with a 32-bit big-endian counter,
+plus a tag computed by GHASH, a polynomial evaluation in GF(2^128) at the hash subkey H = E(K, 0^128)
+over the additional authenticated data, the ciphertext and their lengths. The tag is that hash XORed
+with the encryption of the first counter block J0.
+
+GHASH is linear in H: reusing a nonce leaks the XOR of two hashes under the same mask, which is what
+nonce-reuse attacks solve for H. The field multiplication, GHASH and J0 are public so they can be
+studied on their own. Blocks are read as big-endian integers, so bit 0 of GCM, the coefficient of
+x^0, is the most significant bit.
+*/
+
+use crate::block_cipher::BlockCipher;
+use crate::challenge18::{CounterLayout, Keystream};
+use crate::challenge5::keystream_xor;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+
+pub const BLOCK_SIZE: usize = 16;
+pub const TAG_SIZE: usize = 16;
+// 96-bit IVs are used as is, any other length is hashed into J0
+pub const IV_SIZE: usize = 12;
+
+pub type Tag = [u8; TAG_SIZE];
+
+// x^128 + x^7 + x^2 + x + 1, reduced and in GCM's reflected bit order
+const R: u128 = 0xe1 << 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcmError {
+    // GCM is only defined for 128-bit block ciphers
+    InvalidBlockSize(usize),
+    EmptyIv,
+    InvalidTag,
+}
+
+impl fmt::Display for GcmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GcmError::InvalidBlockSize(block_size) => {
+                write!(f, "GCM needs a 16 byte block, not {}", block_size)
+            }
+            GcmError::EmptyIv => write!(f, "IV must not be empty"),
+            GcmError::InvalidTag => write!(f, "authentication tag doesn't match"),
+        }
+    }
+}
+
+impl Error for GcmError {}
+
+fn to_block(x: u128) -> [u8; BLOCK_SIZE] {
+    x.to_be_bytes()
+}
+
+// Zero padded when shorter than a block
+fn from_block(bytes: &[u8]) -> u128 {
+    let mut block = [0u8; BLOCK_SIZE];
+    block[..bytes.len()].copy_from_slice(bytes);
+    u128::from_be_bytes(block)
+}
+
+// Multiplication in GF(2^128), algorithm 1 of SP 800-38D. Branch free, so the time taken doesn't
+// depend on either operand.
+pub fn gf128_mul(x: u128, y: u128) -> u128 {
+    let mut z = 0u128;
+    let mut v = y;
+    for i in 0..128 {
+        let bit = (x >> (127 - i)) & 1;
+        z ^= v & bit.wrapping_neg();
+        v = (v >> 1) ^ (R & (v & 1).wrapping_neg());
+    }
+    z
+}
+
+// GHASH state: every block is added in and the sum multiplied by H
+#[derive(Debug, Clone)]
+pub struct Ghash {
+    h: u128,
+    y: u128,
+}
+
+impl Ghash {
+    pub fn new(h: u128) -> Self {
+        Ghash { h, y: 0 }
+    }
+
+    // Hash `data` zero padded to a whole number of blocks
+    pub fn update(&mut self, data: &[u8]) {
+        for block in data.chunks(BLOCK_SIZE) {
+            self.y = gf128_mul(self.y ^ from_block(block), self.h);
+        }
+    }
+
+    pub fn value(&self) -> u128 {
+        self.y
+    }
+}
+
+// GHASH over the AAD and the ciphertext, each zero padded, then their lengths in bits
+pub fn ghash(h: u128, aad: &[u8], ciphertext: &[u8]) -> u128 {
+    let mut ghash = Ghash::new(h);
+    ghash.update(aad);
+    ghash.update(ciphertext);
+    let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+    ghash.update(&to_block(lengths));
+    ghash.value()
+}
+
+fn check_block_size<C: BlockCipher>(cipher: &C) -> Result<(), GcmError> {
+    if cipher.block_size() != BLOCK_SIZE {
+        return Err(GcmError::InvalidBlockSize(cipher.block_size()));
+    }
+    Ok(())
+}
+
+// H = E(K, 0^128)
+pub fn hash_subkey<C: BlockCipher>(cipher: &C) -> Result<u128, GcmError> {
+    check_block_size(cipher)?;
+    let mut block = [0u8; BLOCK_SIZE];
+    cipher.encrypt_block(&mut block);
+    Ok(u128::from_be_bytes(block))
+}
+
+// First counter block: IV || 0^31 || 1 for a 96-bit IV, GHASH of the IV otherwise
+pub fn j0(h: u128, iv: &[u8]) -> Result<u128, GcmError> {
+    if iv.is_empty() {
+        return Err(GcmError::EmptyIv);
+    }
+    if iv.len() == IV_SIZE {
+        return Ok(from_block(iv) | 1);
+    }
+    Ok(ghash(h, &[], iv))
+}
+
+// Keystream for the data starts at inc32(J0), J0 itself only masks the tag
+fn ctr<C: BlockCipher>(cipher: &C, j0: u128, data: &[u8]) -> Vec<u8> {
+    let counter_block = to_block(j0);
+    let counter = u32::from_be_bytes(counter_block[IV_SIZE..].try_into().unwrap());
+    let initial_counter = counter.wrapping_add(1) as u128;
+    let keystream = Keystream::new(
+        cipher,
+        CounterLayout::GCM,
+        &counter_block[..IV_SIZE],
+        initial_counter,
+    )
+    .expect("16 byte block and 32-bit counter");
+    keystream_xor(data, keystream)
+}
+
+fn tag<C: BlockCipher>(cipher: &C, h: u128, j0: u128, aad: &[u8], ciphertext: &[u8]) -> Tag {
+    let mut mask = to_block(j0);
+    cipher.encrypt_block(&mut mask);
+    to_block(u128::from_be_bytes(mask) ^ ghash(h, aad, ciphertext))
+}
+
+// Whether both inputs are equal, looking at every byte whatever the first difference is
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn gcm_encrypt<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<(Vec<u8>, Tag), GcmError> {
+    let h = hash_subkey(cipher)?;
+    let j0 = j0(h, iv)?;
+    let ciphertext = ctr(cipher, j0, plaintext);
+    let tag = tag(cipher, h, j0, aad, &ciphertext);
+    Ok((ciphertext, tag))
+}
+
+// The plaintext is only returned when the tag checks out
+pub fn gcm_decrypt<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    expected_tag: &[u8],
+) -> Result<Vec<u8>, GcmError> {
+    let h = hash_subkey(cipher)?;
+    let j0 = j0(h, iv)?;
+    if !constant_time_eq(&tag(cipher, h, j0, aad, ciphertext), expected_tag) {
+        return Err(GcmError::InvalidTag);
+    }
+    Ok(ctr(cipher, j0, ciphertext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::Aes;
+    use crate::block_cipher::XorCipher;
+    use crate::challenge1::{hex_decode, hex_encode};
+
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308";
+    const PLAINTEXT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255";
+    const AAD: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+
+    // Test cases from the GCM specification (McGrew and Viega), all with AES-128
+    fn check(key: &str, iv: &str, aad: &str, plaintext: &str, ciphertext: &str, tag: &str) {
+        let aes = Aes::new(&hex_decode(key).unwrap()).unwrap();
+        let (iv, aad) = (hex_decode(iv).unwrap(), hex_decode(aad).unwrap());
+        let plaintext = hex_decode(plaintext).unwrap();
+        let res = gcm_encrypt(&aes, &iv, &aad, &plaintext);
+        assert!(res.is_ok());
+        let (encrypted, computed_tag) = res.unwrap();
+        assert_eq!(hex_encode(&encrypted), ciphertext);
+        assert_eq!(hex_encode(&computed_tag), tag);
+        let res = gcm_decrypt(&aes, &iv, &aad, &encrypted, &computed_tag);
+        assert_eq!(res, Ok(plaintext));
+    }
+
+    #[test]
+    fn test_gcm_empty_and_zero() {
+        let zero_key = "00000000000000000000000000000000";
+        let zero_iv = "000000000000000000000000";
+        check(
+            zero_key,
+            zero_iv,
+            "",
+            "",
+            "",
+            "58e2fccefa7e3061367f1d57a4e7455a",
+        );
+        check(
+            zero_key,
+            zero_iv,
+            "",
+            zero_key,
+            "0388dace60b6a392f328c2b971b2fe78",
+            "ab6e47d42cec13bdf53a67b21257bddf",
+        );
+    }
+
+    #[test]
+    fn test_gcm_with_aad() {
+        check(
+            KEY,
+            "cafebabefacedbaddecaf888",
+            "",
+            PLAINTEXT,
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985",
+            "4d5c2af327cd64a62cf35abd2ba6fab4",
+        );
+        check(
+            KEY,
+            "cafebabefacedbaddecaf888",
+            AAD,
+            &PLAINTEXT[..120],
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+            "5bc94fbc3221a5db94fae95ae7121a47",
+        );
+    }
+
+    #[test]
+    fn test_gcm_iv_lengths() {
+        check(
+            KEY,
+            "cafebabefacedbad",
+            AAD,
+            &PLAINTEXT[..120],
+            "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+             73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+            "3612d2e79e3b0785561be14aaca2fccb",
+        );
+        check(
+            KEY,
+            "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+             c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+            AAD,
+            &PLAINTEXT[..120],
+            "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+             01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+            "619cc5aefffe0bfa462af43c1699d050",
+        );
+    }
+
+    #[test]
+    fn test_ghash() {
+        let aes = Aes::new(&[0u8; 16]).unwrap();
+        let h = hash_subkey(&aes).unwrap();
+        assert_eq!(hex_encode(&to_block(h)), "66e94bd4ef8a2c3b884cfa59ca342b2e");
+        let ciphertext = hex_decode("0388dace60b6a392f328c2b971b2fe78").unwrap();
+        assert_eq!(
+            hex_encode(&to_block(ghash(h, &[], &ciphertext))),
+            "f38cbb1ad69223dcc3457ae5b6b0f885"
+        );
+        // 1 is the most significant bit, and x^127 * x wraps around to x^7 + x^2 + x + 1
+        let one = 1 << 127;
+        assert_eq!(gf128_mul(one, h), h);
+        assert_eq!(gf128_mul(1, 1 << 126), R);
+    }
+
+    #[test]
+    fn test_nonce_reuse_cancels_tag_mask() {
+        let aes = Aes::new(&hex_decode(KEY).unwrap()).unwrap();
+        let iv = [7u8; IV_SIZE];
+        let (c1, t1) = gcm_encrypt(&aes, &iv, b"", b"attack at dawn!!").unwrap();
+        let (c2, t2) = gcm_encrypt(&aes, &iv, b"", b"attack at dusk!!").unwrap();
+        let h = hash_subkey(&aes).unwrap();
+        let tags = u128::from_be_bytes(t1) ^ u128::from_be_bytes(t2);
+        assert_eq!(tags, ghash(h, &[], &c1) ^ ghash(h, &[], &c2));
+    }
+
+    #[test]
+    fn test_gcm_errors() {
+        let aes = Aes::new(&[0u8; 16]).unwrap();
+        let (ciphertext, mut tag) = gcm_encrypt(&aes, &[0u8; 12], b"aad", b"text").unwrap();
+        assert_eq!(
+            gcm_decrypt(&aes, &[0u8; 12], b"aad!", &ciphertext, &tag),
+            Err(GcmError::InvalidTag)
+        );
+        tag[15] ^= 1;
+        assert_eq!(
+            gcm_decrypt(&aes, &[0u8; 12], b"aad", &ciphertext, &tag),
+            Err(GcmError::InvalidTag)
+        );
+        assert_eq!(
+            gcm_decrypt(&aes, &[0u8; 12], b"aad", &ciphertext, &tag[..8]),
+            Err(GcmError::InvalidTag)
+        );
+        assert_eq!(gcm_encrypt(&aes, &[], b"", b"text"), Err(GcmError::EmptyIv));
+        let toy = XorCipher::new(&[1u8; 8]).unwrap();
+        assert_eq!(
+            gcm_encrypt(&toy, &[0u8; 12], b"", b"text"),
+            Err(GcmError::InvalidBlockSize(8))
+        );
+        assert!(constant_time_eq(b"same", b"same"));
+        assert!(!constant_time_eq(b"same", b"sane"));
+    }
+}
@@ -1,10 +1,24 @@
-#![feature(assoc_char_funcs)]
-
+pub mod aes;
+pub mod bits;
+#[allow(dead_code)]
+mod block_cipher;
+#[allow(dead_code)]
+mod blocks;
+#[allow(dead_code)]
+mod cfb;
 #[allow(dead_code)]
 mod challenge1;
 #[allow(dead_code)]
+mod challenge10;
+#[allow(dead_code)]
+mod challenge11;
+#[allow(dead_code)]
+mod challenge18;
+#[allow(dead_code)]
 mod challenge2;
 #[allow(dead_code)]
+mod challenge25;
+#[allow(dead_code)]
 mod challenge3;
 #[allow(dead_code)]
 mod challenge4;
@@ -12,3 +26,21 @@ mod challenge4;
 mod challenge5;
 #[allow(dead_code)]
 mod challenge6;
+#[allow(dead_code)]
+mod challenge7;
+#[allow(dead_code)]
+mod challenge8;
+#[allow(dead_code)]
+mod challenge9;
+#[allow(dead_code)]
+mod classifier;
+#[allow(dead_code)]
+mod cribs;
+#[allow(dead_code)]
+mod gcm;
+#[allow(dead_code)]
+mod loader;
+#[allow(dead_code)]
+mod ofb;
+#[allow(dead_code)]
+mod oracle;
@@ -0,0 +1,327 @@
+/*
+Unified input loader for challenge data files
+
+Challenge data comes as hex encoded lines (challenge 4), a base64 blob wrapped over several lines
+(challenge 6), base64 encoded lines or plain bytes. Read any of them from a file or stdin and decode
+it, reporting where decoding failed instead of silently skipping input.
+*/
+
+use crate::challenge1::hex_decode;
+use crate::challenge6::base64_decode_bytes;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub enum Source {
+    File(PathBuf),
+    Stdin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    HexLines,
+    Base64Blob,
+    Base64Lines,
+    Raw,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    InvalidChar { line: usize, ch: char },
+    OddHexLength { line: usize },
+    // Base64 comes in groups of 4 chars, `len` doesn't count whitespace
+    InvalidBase64Length { line: usize, len: usize },
+    // '=' anywhere but in the one or two last chars of the base64 input
+    MisplacedPadding { line: usize },
+    NotUtf8,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(inner) => write!(f, "could not read input: {}", inner),
+            LoadError::InvalidChar { line, ch } => {
+                write!(f, "invalid char {:?} on line {}", ch, line)
+            }
+            LoadError::OddHexLength { line } => {
+                write!(f, "odd number of hex digits on line {}", line)
+            }
+            LoadError::InvalidBase64Length { line, len } => write!(
+                f,
+                "base64 length {} ending on line {} is not a multiple of 4",
+                len, line
+            ),
+            LoadError::MisplacedPadding { line } => {
+                write!(f, "misplaced base64 padding on line {}", line)
+            }
+            LoadError::NotUtf8 => write!(f, "encoded input is not valid UTF-8"),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Loaded {
+    Lines(Vec<Vec<u8>>),
+    Blob(Vec<u8>),
+}
+
+impl Loaded {
+    // All the decoded bytes, lines are concatenated
+    pub fn into_blob(self) -> Vec<u8> {
+        match self {
+            Loaded::Lines(lines) => lines.concat(),
+            Loaded::Blob(blob) => blob,
+        }
+    }
+
+    // The decoded lines, a blob is a single line
+    pub fn into_lines(self) -> Vec<Vec<u8>> {
+        match self {
+            Loaded::Lines(lines) => lines,
+            Loaded::Blob(blob) => vec![blob],
+        }
+    }
+}
+
+pub fn read_source(source: &Source) -> Result<Vec<u8>, LoadError> {
+    match source {
+        Source::File(path) => Ok(fs::read(path)?),
+        Source::Stdin => {
+            let mut input = Vec::new();
+            io::stdin().lock().read_to_end(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+// Report the first char `is_valid` rejects together with its line, `text` starts on `first_line`.
+// Decoders only see bytes, so they would report the first byte of a multi-byte char instead.
+fn check_chars<F>(text: &str, first_line: usize, is_valid: F) -> Result<(), LoadError>
+where
+    F: Fn(char) -> bool,
+{
+    for (index, line) in text.lines().enumerate() {
+        if let Some(ch) = line.chars().find(|&ch| !is_valid(ch)) {
+            return Err(LoadError::InvalidChar {
+                line: first_line + index,
+                ch,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn is_base64_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '+' || ch == '/' || ch == '=' || ch.is_whitespace()
+}
+
+fn decode_hex_line(line_number: usize, line: &str) -> Result<Vec<u8>, LoadError> {
+    check_chars(line, line_number, |ch| ch.is_ascii_hexdigit())?;
+    if !line.len().is_multiple_of(2) {
+        return Err(LoadError::OddHexLength { line: line_number });
+    }
+    Ok(hex_decode(line).expect("only hex digits"))
+}
+
+fn without_whitespace(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+// Offset of the first '=' that isn't part of the final padding of at most two chars
+fn misplaced_padding(compact: &str) -> Option<usize> {
+    let data_len = compact.trim_end_matches('=').len();
+    match compact.find('=') {
+        Some(offset) if offset < data_len => Some(offset),
+        _ if compact.len() - data_len > 2 => Some(data_len + 2),
+        _ => None,
+    }
+}
+
+fn decode_base64_line(line_number: usize, line: &str) -> Result<Vec<u8>, LoadError> {
+    check_chars(line, line_number, is_base64_char)?;
+    let compact = without_whitespace(line);
+    if misplaced_padding(&compact).is_some() {
+        return Err(LoadError::MisplacedPadding { line: line_number });
+    }
+    if !compact.len().is_multiple_of(4) {
+        return Err(LoadError::InvalidBase64Length {
+            line: line_number,
+            len: compact.len(),
+        });
+    }
+    Ok(base64_decode_bytes(line).expect("only base64 chars"))
+}
+
+// Line holding the non whitespace char at `offset`, starting at 1
+fn line_of_offset(text: &str, offset: usize) -> usize {
+    let mut seen = 0;
+    for (index, line) in text.lines().enumerate() {
+        seen += line.chars().filter(|c| !c.is_whitespace()).count();
+        if seen > offset {
+            return index + 1;
+        }
+    }
+    text.lines().count()
+}
+
+fn decode_base64_blob(text: &str) -> Result<Vec<u8>, LoadError> {
+    check_chars(text, 1, is_base64_char)?;
+    let compact = without_whitespace(text);
+    if let Some(offset) = misplaced_padding(&compact) {
+        return Err(LoadError::MisplacedPadding {
+            line: line_of_offset(text, offset.min(compact.len() - 1)),
+        });
+    }
+    if !compact.len().is_multiple_of(4) {
+        return Err(LoadError::InvalidBase64Length {
+            line: line_of_offset(text, compact.len() - 1),
+            len: compact.len(),
+        });
+    }
+    Ok(base64_decode_bytes(text).expect("only base64 chars"))
+}
+
+// Decode every non blank line, line numbers in errors start at 1
+fn decode_lines<F>(text: &str, decode_line: F) -> Result<Vec<Vec<u8>>, LoadError>
+where
+    F: Fn(usize, &str) -> Result<Vec<u8>, LoadError>,
+{
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(line_number, line)| decode_line(line_number, line))
+        .collect()
+}
+
+fn as_text(input: &[u8]) -> Result<&str, LoadError> {
+    std::str::from_utf8(input).map_err(|_| LoadError::NotUtf8)
+}
+
+// Decode input already read into memory
+pub fn decode(input: &[u8], format: Format) -> Result<Loaded, LoadError> {
+    match format {
+        Format::Raw => Ok(Loaded::Blob(input.to_vec())),
+        Format::HexLines => Ok(Loaded::Lines(decode_lines(
+            as_text(input)?,
+            decode_hex_line,
+        )?)),
+        Format::Base64Lines => Ok(Loaded::Lines(decode_lines(
+            as_text(input)?,
+            decode_base64_line,
+        )?)),
+        Format::Base64Blob => Ok(Loaded::Blob(decode_base64_blob(as_text(input)?)?)),
+    }
+}
+
+pub fn load(source: &Source, format: Format) -> Result<Loaded, LoadError> {
+    decode(&read_source(source)?, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str) -> Source {
+        Source::File(PathBuf::from(path))
+    }
+
+    #[test]
+    fn test_load_hex_lines() {
+        let res = load(
+            &file("src/challenge4/input_challenge4.txt"),
+            Format::HexLines,
+        );
+        assert!(res.is_ok());
+        let lines = res.unwrap().into_lines();
+        assert_eq!(lines.len(), 327);
+        assert_eq!(lines[0].len(), 30);
+    }
+
+    #[test]
+    fn test_load_base64_blob_and_lines() {
+        let blob = load(&file("src/challenge6/6.txt"), Format::Base64Blob);
+        assert!(blob.is_ok());
+        let blob = blob.unwrap().into_blob();
+        assert_eq!(blob.len(), 2876);
+        let lines = load(&file("src/challenge6/6.txt"), Format::Base64Lines);
+        assert!(lines.is_ok());
+        assert_eq!(lines.unwrap().into_blob(), blob);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        match decode(b"4927\n492\n", Format::HexLines) {
+            Err(LoadError::OddHexLength { line }) => assert_eq!(line, 2),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match decode(b"SSdt\nIG*p\n", Format::Base64Blob) {
+            Err(LoadError::InvalidChar { line, ch }) => assert_eq!((line, ch), (2, '*')),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // Non-ASCII chars are reported whole, on their own line
+        for &format in &[Format::Base64Blob, Format::Base64Lines] {
+            match decode(b"SSdt\nIG\xc3\xa90\n", format) {
+                Err(LoadError::InvalidChar { line, ch }) => assert_eq!((line, ch), (2, '\u{e9}')),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        match decode(b"4927\n49\xc3\xa9\n", Format::HexLines) {
+            Err(LoadError::InvalidChar { line, ch }) => assert_eq!((line, ch), (2, '\u{e9}')),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            load(&file("src/does/not/exist.txt"), Format::Raw),
+            Err(LoadError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_malformed_base64() {
+        for &format in &[Format::Base64Blob, Format::Base64Lines] {
+            match decode(b"SS=dt", format) {
+                Err(LoadError::MisplacedPadding { line }) => assert_eq!(line, 1),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            match decode(b"SSdt\nS===\n", format) {
+                Err(LoadError::MisplacedPadding { line }) => assert_eq!(line, 2),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            match decode(b"S", format) {
+                Err(LoadError::InvalidBase64Length { line, len }) => {
+                    assert_eq!((line, len), (1, 1))
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(decode(b"SSdtIG1p\nbGxp\n", format).is_ok());
+            assert!(decode(b"SSdtIG0=\n", format).is_ok());
+        }
+        match decode(b"SSdtIG1p\nbGx\n", Format::Base64Lines) {
+            Err(LoadError::InvalidBase64Length { line, len }) => assert_eq!((line, len), (2, 3)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let challenge6 = fs::read("src/challenge6/6.txt").unwrap();
+        let text = String::from_utf8(challenge6).unwrap();
+        let truncated = text.trim_end();
+        let truncated = &truncated[..truncated.len() - 1];
+        match decode(truncated.as_bytes(), Format::Base64Blob) {
+            Err(LoadError::InvalidBase64Length { line, len }) => {
+                assert_eq!(line, truncated.lines().count());
+                assert_eq!(len % 4, 3);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+/*
+Output feedback (OFB) mode
+
+The IV is encrypted over and over, and the successive outputs are the keystream XORed with the
+plaintext, so like CTR it needs no padding and decryption is encryption. The keystream only depends
+on the key and the IV: reusing an IV reuses the keystream, and the ciphertexts can be broken as a
+many-time pad, one keystream byte at a time as single-character XOR over every ciphertext.
+*/
+
+use crate::block_cipher::{check_iv, BlockCipher, InvalidIvLengthError};
+use crate::challenge1::hex_encode;
+use crate::challenge3::{ngram_score, rank_messages_and_keys};
+use crate::challenge5::keystream_xor;
+
+// The keystream: E(IV), E(E(IV)), ... one byte at a time. It never ends.
+#[derive(Debug, Clone)]
+pub struct Keystream<'a, C> {
+    cipher: &'a C,
+    block: Vec<u8>,
+    // Next byte of `block` to hand out, a full block means it has to be encrypted again
+    position: usize,
+}
+
+impl<'a, C: BlockCipher> Keystream<'a, C> {
+    pub fn new(cipher: &'a C, iv: &[u8]) -> Result<Self, InvalidIvLengthError> {
+        check_iv(cipher, iv)?;
+        Ok(Keystream {
+            cipher,
+            block: iv.to_vec(),
+            position: iv.len(),
+        })
+    }
+}
+
+impl<'a, C: BlockCipher> Iterator for Keystream<'a, C> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.position == self.block.len() {
+            self.cipher.encrypt_block(&mut self.block);
+            self.position = 0;
+        }
+        self.position += 1;
+        Some(self.block[self.position - 1])
+    }
+}
+
+// Encrypts and decrypts alike
+pub fn ofb_apply<C: BlockCipher>(
+    cipher: &C,
+    iv: &[u8],
+    data: &[u8],
+) -> Result<Vec<u8>, InvalidIvLengthError> {
+    Ok(keystream_xor(data, Keystream::new(cipher, iv)?))
+}
+
+// Fewest ciphertexts a keystream byte is recovered from, fewer bytes are too few to score
+const MIN_COLUMN_CIPHERTEXTS: usize = 8;
+// Best single-character keys of a column rescored against the neighbouring columns
+const CANDIDATE_KEYS: usize = 16;
+
+// Byte i of every ciphertext long enough to have one
+fn column(ciphertexts: &[Vec<u8>], i: usize) -> Vec<u8> {
+    ciphertexts
+        .iter()
+        .filter_map(|c| c.get(i))
+        .copied()
+        .collect()
+}
+
+// English score of the bytes around position i once decrypted with `keystream`
+fn context_score(ciphertexts: &[Vec<u8>], keystream: &[u8], i: usize) -> f64 {
+    let start = i.saturating_sub(1);
+    ciphertexts
+        .iter()
+        .filter(|c| c.len() > i)
+        .map(|c| {
+            let end = (i + 2).min(c.len()).min(keystream.len());
+            ngram_score(&keystream_xor(
+                &c[start..end],
+                keystream[start..end].iter().copied(),
+            ))
+        })
+        .sum()
+}
+
+// Keystream shared by ciphertexts encrypted under the same key and IV. Byte i starts as the
+// single-character XOR key of the column made of byte i of every ciphertext, then the best few keys
+// of the column are rescored together with the neighbouring columns: a column of capitals alone
+// can't be told apart from other letters, but the pairs it makes with the next column can. The
+// keystream stops where fewer than MIN_COLUMN_CIPHERTEXTS ciphertexts are left.
+pub fn recover_reused_keystream(ciphertexts: &[Vec<u8>]) -> Vec<u8> {
+    let min_ciphertexts = MIN_COLUMN_CIPHERTEXTS.min(ciphertexts.len()).max(1);
+    let len = (0..)
+        .take_while(|&i| ciphertexts.iter().filter(|c| c.len() > i).count() >= min_ciphertexts)
+        .count();
+    let candidates: Vec<Vec<u8>> = (0..len)
+        .map(|i| {
+            rank_messages_and_keys(&hex_encode(&column(ciphertexts, i)))
+                .iter()
+                .take(CANDIDATE_KEYS)
+                .map(|bundle| bundle.key)
+                .collect()
+        })
+        .collect();
+    let mut keystream: Vec<u8> = candidates.iter().map(|keys| keys[0]).collect();
+    for (i, keys) in candidates.iter().enumerate() {
+        let mut best = (keystream[i], f64::NEG_INFINITY);
+        for &key in keys {
+            keystream[i] = key;
+            let score = context_score(ciphertexts, &keystream, i);
+            if score > best.1 {
+                best = (key, score);
+            }
+        }
+        keystream[i] = best.0;
+    }
+    keystream
+}
+
+// Plaintexts of ciphertexts encrypted under the same key and IV, as far as the keystream could be
+// recovered
+pub fn break_reused_iv(ciphertexts: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let keystream = recover_reused_keystream(ciphertexts);
+    ciphertexts
+        .iter()
+        .map(|ciphertext| keystream_xor(ciphertext, keystream.iter().copied()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenge1::hex_decode;
+    use crate::challenge7::{aes_128, lyrics};
+
+    #[test]
+    fn test_ofb_known_answer() {
+        let aes = aes_128(&hex_decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap()).unwrap();
+        let iv = hex_decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let plaintext = hex_decode(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        )
+        .unwrap();
+        let res = ofb_apply(&aes, &iv, &plaintext);
+        assert!(res.is_ok());
+        let ciphertext = res.unwrap();
+        assert_eq!(
+            hex_encode(&ciphertext),
+            "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+             9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e"
+        );
+        assert_eq!(ofb_apply(&aes, &iv, &ciphertext).unwrap(), plaintext);
+        // No padding: a partial block uses part of the keystream
+        let res = ofb_apply(&aes, &iv, &plaintext[..20]);
+        assert_eq!(res.unwrap(), ciphertext[..20].to_vec());
+        assert_eq!(
+            ofb_apply(&aes, &iv[..8], &plaintext),
+            Err(InvalidIvLengthError {
+                len: 8,
+                block_size: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_break_reused_iv() {
+        let lyrics = lyrics();
+        let lines: Vec<&[u8]> = lyrics
+            .split(|&b| b == b'\n')
+            .filter(|line| line.len() > 20)
+            .collect();
+        let aes = aes_128(b"YELLOW SUBMARINE").unwrap();
+        let iv = b"reused every time";
+        let ciphertexts: Vec<Vec<u8>> = lines
+            .iter()
+            .map(|line| ofb_apply(&aes, &iv[..16], line).unwrap())
+            .collect();
+        let plaintexts = break_reused_iv(&ciphertexts);
+        // Only the last few columns, which fewer than MIN_COLUMN_CIPHERTEXTS lines reach, are left out
+        let recovered = plaintexts.iter().map(Vec::len).max().unwrap();
+        assert!(recovered >= 50);
+        for (plaintext, line) in plaintexts.iter().zip(&lines) {
+            assert_eq!(plaintext.len(), line.len().min(recovered));
+            assert_eq!(plaintext[..], line[..plaintext.len()]);
+        }
+    }
+}
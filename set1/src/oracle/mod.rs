@@ -0,0 +1,197 @@
+/*
+Black-box oracles
+
+Most attacks only get to talk to a black box: they send it bytes and look at what comes back, an
+encryption, a padding check, a yes or no. The Oracle trait is that black box, and closures taking
+bytes are oracles too. Accounting wraps an oracle to count the queries an attack needs, stop it once
+it runs over an optional budget and keep a transcript of every exchange.
+*/
+
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+
+pub trait Oracle {
+    type Output;
+
+    fn query(&mut self, input: &[u8]) -> Self::Output;
+}
+
+impl<F, T> Oracle for F
+where
+    F: FnMut(&[u8]) -> T,
+{
+    type Output = T;
+
+    fn query(&mut self, input: &[u8]) -> T {
+        self(input)
+    }
+}
+
+// What an attack gets back from a query: a plain answer, or one an oracle such as Accounting may
+// refuse to give. Lets an attack take any oracle and pass the refusal on.
+pub trait Answer {
+    type Value;
+    type Error;
+
+    fn into_result(self) -> Result<Self::Value, Self::Error>;
+}
+
+impl Answer for Vec<u8> {
+    type Value = Vec<u8>;
+    type Error = Infallible;
+
+    fn into_result(self) -> Result<Vec<u8>, Infallible> {
+        Ok(self)
+    }
+}
+
+impl<T, E> Answer for Result<T, E> {
+    type Value = T;
+    type Error = E;
+
+    fn into_result(self) -> Result<T, E> {
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExhaustedError {
+    pub budget: usize,
+}
+
+impl fmt::Display for BudgetExhaustedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "query budget of {} exhausted", self.budget)
+    }
+}
+
+impl Error for BudgetExhaustedError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exchange<T> {
+    pub input: Vec<u8>,
+    pub output: T,
+}
+
+#[derive(Debug)]
+pub struct Accounting<O: Oracle> {
+    inner: O,
+    queries: usize,
+    budget: Option<usize>,
+    // None unless recording
+    transcript: Option<Vec<Exchange<O::Output>>>,
+}
+
+impl<O: Oracle> Accounting<O> {
+    pub fn new(inner: O) -> Self {
+        Accounting {
+            inner,
+            queries: 0,
+            budget: None,
+            transcript: None,
+        }
+    }
+
+    // Refuse queries once `budget` have been answered
+    pub fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    // Keep every input and output
+    pub fn recording(mut self) -> Self {
+        self.transcript = Some(vec![]);
+        self
+    }
+
+    // Queries answered so far, refused ones don't count
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    pub fn remaining(&self) -> Option<usize> {
+        self.budget.map(|budget| budget - self.queries)
+    }
+
+    // Empty unless recording
+    pub fn transcript(&self) -> &[Exchange<O::Output>] {
+        self.transcript.as_deref().unwrap_or(&[])
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+}
+
+impl<O> Oracle for Accounting<O>
+where
+    O: Oracle,
+    O::Output: Clone,
+{
+    type Output = Result<O::Output, BudgetExhaustedError>;
+
+    fn query(&mut self, input: &[u8]) -> Self::Output {
+        if let Some(budget) = self.budget {
+            if self.queries >= budget {
+                return Err(BudgetExhaustedError { budget });
+            }
+        }
+        self.queries += 1;
+        let output = self.inner.query(input);
+        if let Some(transcript) = self.transcript.as_mut() {
+            transcript.push(Exchange {
+                input: input.to_vec(),
+                output: output.clone(),
+            });
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closure_oracle() {
+        let mut calls = 0;
+        let mut oracle = |input: &[u8]| {
+            calls += 1;
+            input.len()
+        };
+        assert_eq!(oracle.query(b"four"), 4);
+        assert_eq!(oracle.query(b""), 0);
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_accounting() {
+        let reverse = |input: &[u8]| input.iter().rev().copied().collect::<Vec<u8>>();
+        let mut oracle = Accounting::new(reverse).with_budget(2).recording();
+        assert_eq!(oracle.remaining(), Some(2));
+        assert_eq!(oracle.query(b"abc"), Ok(b"cba".to_vec()));
+        assert_eq!(oracle.query(b"de"), Ok(b"ed".to_vec()));
+        assert_eq!(oracle.query(b"f"), Err(BudgetExhaustedError { budget: 2 }));
+        assert_eq!((oracle.queries(), oracle.remaining()), (2, Some(0)));
+        assert_eq!(
+            oracle.transcript(),
+            &[
+                Exchange {
+                    input: b"abc".to_vec(),
+                    output: b"cba".to_vec()
+                },
+                Exchange {
+                    input: b"de".to_vec(),
+                    output: b"ed".to_vec()
+                }
+            ]
+        );
+        let mut unlimited = Accounting::new(|input: &[u8]| input.len());
+        for _ in 0..10 {
+            assert!(unlimited.query(b"x").is_ok());
+        }
+        assert_eq!((unlimited.queries(), unlimited.remaining()), (10, None));
+        assert!(unlimited.transcript().is_empty());
+    }
+}